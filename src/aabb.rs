@@ -10,6 +10,18 @@ pub struct AaBb {
     pub z: Interval,
 }
 
+#[cfg(test)]
+thread_local! {
+    /// number of `AaBb::hit` calls on this thread (to compare BVH builds in tests)
+    pub(crate) static HIT_COUNT: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+impl Default for AaBb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AaBb {
     /// initialize by default Interval values
     pub fn new() -> Self {
@@ -61,6 +73,19 @@ impl AaBb {
         aabb
     }
 
+    /// expand axes thinner than `delta` to `delta` so that flat boxes can still be hit
+    pub fn pad_to_minimums(&self, delta: f64) -> Self {
+        let pad = |interval: &Interval| {
+            if interval.size() < delta {
+                interval.expand(delta)
+            } else {
+                interval.clone()
+            }
+        };
+
+        Self::new_by_values(&pad(&self.x), &pad(&self.y), &pad(&self.z))
+    }
+
     pub fn axis_interval(&self, n: usize) -> &Interval {
         match n {
             1 => &self.y,
@@ -69,7 +94,44 @@ impl AaBb {
         }
     }
 
+    /// index of the axis with the largest extent (0: x, 1: y, 2: z)
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() {
+                0
+            } else {
+                2
+            }
+        } else if self.y.size() > self.z.size() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// surface area of the box (0 for an empty box)
+    pub fn surface_area(&self) -> f64 {
+        let (dx, dy, dz) = (
+            self.x.size().max(0.0),
+            self.y.size().max(0.0),
+            self.z.size().max(0.0),
+        );
+
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::from_slice([
+            0.5 * (self.x.min + self.x.max),
+            0.5 * (self.y.min + self.y.max),
+            0.5 * (self.z.min + self.z.max),
+        ])
+    }
+
     pub fn hit(&self, ray: &Ray, mut ray_t: Interval) -> bool {
+        #[cfg(test)]
+        HIT_COUNT.with(|count| count.set(count.get() + 1));
+
         let ray_origin = ray.origin();
         let ray_direction = ray.direction();
 
@@ -91,10 +153,10 @@ impl AaBb {
                 }
             } else {
                 if t_1 > ray_t.min {
-                    ray_t.min = t_0;
+                    ray_t.min = t_1;
                 }
                 if t_0 < ray_t.max {
-                    ray_t.max = t_1;
+                    ray_t.max = t_0;
                 }
             }
 
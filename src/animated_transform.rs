@@ -0,0 +1,388 @@
+use crate::aabb::AaBb;
+use crate::transform::Transform;
+use crate::vec3::{Point3, Vec3};
+use std::error::Error;
+
+/// unit quaternion for rotations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Quaternion {
+    /// no rotation
+    pub fn new() -> Self {
+        Self {
+            w: 1.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+
+    /// rotation by `degrees` around `axis`, same as `Transform::rotate`
+    pub fn from_axis_angle(axis: &Vec3, degrees: f64) -> Self {
+        let [x, y, z] = Vec3::new_unit_vec(axis.clone()).e;
+        let (sin, cos) = (0.5 * degrees.to_radians()).sin_cos();
+        Self {
+            w: cos,
+            x: sin * x,
+            y: sin * y,
+            z: sin * z,
+        }
+    }
+
+    pub fn dot(&self, other: &Self) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn normalized(&self) -> Self {
+        let norm = self.dot(self).sqrt();
+        self.scaled(1.0 / norm)
+    }
+
+    fn scaled(&self, s: f64) -> Self {
+        Self {
+            w: s * self.w,
+            x: s * self.x,
+            y: s * self.y,
+            z: s * self.z,
+        }
+    }
+
+    fn added(&self, other: &Self) -> Self {
+        Self {
+            w: self.w + other.w,
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+
+    /// spherical interpolation along the shorter way from `self` (t = 0) to `other` (t = 1)
+    pub fn slerp(&self, other: &Self, t: f64) -> Self {
+        let (other, cos) = if self.dot(other) < 0.0 {
+            (other.scaled(-1.0), -self.dot(other))
+        } else {
+            (*other, self.dot(other))
+        };
+
+        if cos > 0.9995 {
+            // nearly the same rotation: linear is accurate and avoids dividing by sin ~ 0
+            return self.scaled(1.0 - t).added(&other.scaled(t)).normalized();
+        }
+        let theta = cos.acos();
+        self.scaled(((1.0 - t) * theta).sin() / theta.sin())
+            .added(&other.scaled((t * theta).sin() / theta.sin()))
+    }
+
+    /// rotation angle (radians) from `self` to `other` along the shorter way
+    pub fn angle_to(&self, other: &Self) -> f64 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    /// rows of the rotation matrix (the quaternion must be normalized)
+    pub fn matrix(&self) -> [[f64; 3]; 3] {
+        let Self { w, x, y, z } = *self;
+        [
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ]
+    }
+}
+
+/// placement at `time`: scaled first, then rotated, then translated
+#[derive(Debug, Clone)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    /// identity at `time`
+    pub fn new(time: f64) -> Self {
+        Self {
+            time,
+            translation: Vec3::new(),
+            rotation: Quaternion::new(),
+            scale: Vec3::from_slice([1.0, 1.0, 1.0]),
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::translate(&self.translation)
+            * Transform::from_rotation(self.rotation.normalized().matrix())
+            * Transform::scale(&self.scale)
+    }
+}
+
+/// transform interpolated between keyframes
+///
+/// translation and scale are linear, rotation is slerp (the shorter way, so a turn of
+/// 180 degrees or more needs keyframes in between). before the first and after the
+/// last keyframe the transform stays the same.
+#[derive(Debug, Clone)]
+pub struct AnimatedTransform {
+    keyframes: Vec<Keyframe>,
+}
+
+/// samples per keyframe interval when bounding the motion
+const BOUND_STEPS: usize = 16;
+
+impl AnimatedTransform {
+    /// error if there are no keyframes or the times are not increasing
+    pub fn new(keyframes: Vec<Keyframe>) -> Result<Self, Box<dyn Error>> {
+        check_times(
+            &keyframes
+                .iter()
+                .map(|keyframe| keyframe.time)
+                .collect::<Vec<_>>(),
+        )?;
+
+        Ok(Self { keyframes })
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn transform(&self, time: f64) -> Transform {
+        self.keyframe(time).transform()
+    }
+
+    /// interpolated keyframe at `time`
+    pub fn keyframe(&self, time: f64) -> Keyframe {
+        let k = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+        if k == 0 || k == self.keyframes.len() {
+            let mut keyframe = self.keyframes[k.saturating_sub(1)].clone();
+            keyframe.time = time;
+            return keyframe;
+        }
+
+        let (a, b) = (&self.keyframes[k - 1], &self.keyframes[k]);
+        interpolate(a, b, (time - a.time) / (b.time - a.time))
+    }
+
+    /// the same motion between `open` and `close` (a camera shutter), with keyframes
+    /// only in this interval so `aabb` bounds just the times the rays can have
+    pub fn over_shutter(&self, open: f64, close: f64) -> Self {
+        let mut keyframes = vec![self.keyframe(open)];
+        keyframes.extend(
+            self.keyframes
+                .iter()
+                .filter(|keyframe| open < keyframe.time && keyframe.time < close)
+                .cloned(),
+        );
+        if open < close {
+            keyframes.push(self.keyframe(close));
+        }
+
+        Self { keyframes }
+    }
+
+    /// box around `bbox` moved over all keyframes (so over any time)
+    ///
+    /// each keyframe interval is sampled and the boxes are padded by the largest
+    /// distance the corners can leave the straight lines between the samples.
+    pub fn aabb(&self, bbox: &AaBb) -> AaBb {
+        if bbox.x.size() < 0.0 || bbox.y.size() < 0.0 || bbox.z.size() < 0.0 {
+            return bbox.clone();
+        }
+
+        let mut motion_box = self.keyframes[0].transform().aabb(bbox);
+        for pair in self.keyframes.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            let mut segment_box = AaBb::new();
+            for step in 0..=BOUND_STEPS {
+                let transform = interpolate(a, b, step as f64 / BOUND_STEPS as f64).transform();
+                segment_box = AaBb::new_by_two_aabb(&segment_box, &transform.aabb(bbox));
+            }
+
+            // p(s) = T(s) + R(s) S(s) c with |p''| <= w^2 |S c| + 2 w |dS c|,
+            // and the chord between samples h apart is off by at most h^2 / 8 |p''|
+            let omega = a.rotation.normalized().angle_to(&b.rotation.normalized());
+            let (mut radius, mut scale_change) = (0.0f64, 0.0f64);
+            for corner in corners(bbox) {
+                let scaled = |s: &Vec3| Vec3::from_slice([0, 1, 2].map(|i| s[i] * corner[i]));
+                radius = radius
+                    .max(scaled(&a.scale).norm())
+                    .max(scaled(&b.scale).norm());
+                scale_change =
+                    scale_change.max(scaled(&(b.scale.clone() - a.scale.clone())).norm());
+            }
+            let h = 1.0 / BOUND_STEPS as f64;
+            let pad = h * h / 8.0 * (omega * omega * radius + 2.0 * omega * scale_change);
+
+            motion_box = AaBb::new_by_two_aabb(
+                &motion_box,
+                &AaBb::new_by_values(
+                    &segment_box.x.expand(2.0 * pad),
+                    &segment_box.y.expand(2.0 * pad),
+                    &segment_box.z.expand(2.0 * pad),
+                ),
+            );
+        }
+
+        motion_box
+    }
+}
+
+/// error if there are no keyframe times or they are not increasing
+pub(crate) fn check_times(times: &[f64]) -> Result<(), Box<dyn Error>> {
+    if times.is_empty() {
+        return Err("no keyframes".into());
+    }
+    for pair in times.windows(2) {
+        if pair[0].partial_cmp(&pair[1]) != Some(std::cmp::Ordering::Less) {
+            return Err(format!(
+                "keyframe times are not increasing: {} then {}",
+                pair[0], pair[1]
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+fn interpolate(a: &Keyframe, b: &Keyframe, s: f64) -> Keyframe {
+    Keyframe {
+        time: a.time + s * (b.time - a.time),
+        translation: (1.0 - s) * a.translation.clone() + s * b.translation.clone(),
+        rotation: a.rotation.normalized().slerp(&b.rotation.normalized(), s),
+        scale: (1.0 - s) * a.scale.clone() + s * b.scale.clone(),
+    }
+}
+
+fn corners(bbox: &AaBb) -> [Point3; 8] {
+    [0, 1, 2, 3, 4, 5, 6, 7].map(|corner| {
+        Point3::from_slice([
+            if corner & 1 == 0 {
+                bbox.x.min
+            } else {
+                bbox.x.max
+            },
+            if corner & 2 == 0 {
+                bbox.y.min
+            } else {
+                bbox.y.max
+            },
+            if corner & 4 == 0 {
+                bbox.z.min
+            } else {
+                bbox.z.max
+            },
+        ])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utl;
+
+    fn assert_near(a: &Vec3, b: &Vec3) {
+        assert!((a.clone() - b.clone()).norm() < 1e-9, "{a} {b}");
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let mut start = Keyframe::new(0.0);
+        start.translation = Vec3::from_slice([1.0, 0.0, 0.0]);
+        let mut end = Keyframe::new(2.0);
+        end.translation = Vec3::from_slice([3.0, 0.0, 0.0]);
+        end.rotation = Quaternion::from_axis_angle(&Vec3::from_slice([0.0, 1.0, 0.0]), 90.0);
+        end.scale = Vec3::from_slice([3.0, 3.0, 3.0]);
+        let motion = AnimatedTransform::new(vec![start, end]).unwrap();
+
+        let p = Point3::from_slice([1.0, 0.0, 0.0]);
+        let expected = Transform::translate(&Vec3::from_slice([2.0, 0.0, 0.0]))
+            * Transform::rotate_y(45.0)
+            * Transform::scale(&Vec3::from_slice([2.0, 2.0, 2.0]));
+        assert_near(&motion.transform(1.0).point(&p), &expected.point(&p));
+        // clamped outside the keyframes
+        assert_near(
+            &motion.transform(-1.0).point(&p),
+            &Point3::from_slice([2.0, 0.0, 0.0]),
+        );
+        assert_near(
+            &motion.transform(5.0).point(&p),
+            &Point3::from_slice([3.0, 0.0, -3.0]),
+        );
+
+        assert!(AnimatedTransform::new(vec![]).is_err());
+        assert!(AnimatedTransform::new(vec![Keyframe::new(1.0), Keyframe::new(1.0)]).is_err());
+    }
+
+    #[test]
+    fn test_motion_bounds() {
+        let mut random = utl::Random::new_by_seed(23);
+        // an arm swinging far from the pivot bulges out between the samples
+        let bbox = AaBb::new_by_two_points(
+            &Point3::from_slice([4.0, -0.1, -0.1]),
+            &Point3::from_slice([5.0, 0.1, 0.1]),
+        );
+        let axis = Vec3::from_slice([0.0, 0.0, 1.0]);
+        let mut keyframes = vec![];
+        for (k, degrees) in [0.0, 170.0, 340.0].into_iter().enumerate() {
+            let mut keyframe = Keyframe::new(k as f64);
+            keyframe.rotation = Quaternion::from_axis_angle(&axis, degrees);
+            keyframe.scale = Vec3::from_slice([1.0 + k as f64, 1.0, 1.0]);
+            keyframes.push(keyframe);
+        }
+        let motion = AnimatedTransform::new(keyframes).unwrap();
+        let motion_box = motion.aabb(&bbox);
+
+        for _ in 0..10000 {
+            let moved = motion
+                .transform(random.random_f64_range(-0.5, 2.5))
+                .aabb(&bbox);
+            for axis in 0..3 {
+                let (outer, inner) = (motion_box.axis_interval(axis), moved.axis_interval(axis));
+                assert!(outer.min <= inner.min && inner.max <= outer.max);
+            }
+        }
+        // but not much larger than the swept circle of radius 15 and a bit
+        assert!(motion_box.x.max < 15.1 && motion_box.y.max < 15.1);
+
+        // the shutter sees only the first half of the swing
+        let shutter = motion.over_shutter(0.25, 0.75);
+        assert_eq!(shutter.keyframes().len(), 2);
+        let shutter_box = shutter.aabb(&bbox);
+        assert!(shutter_box.y.min > -0.2 && shutter_box.x.max < motion_box.x.max);
+        for _ in 0..1000 {
+            let time = random.random_f64_range(0.25, 0.75);
+            let p = Point3::from_slice([4.5, 0.0, 0.0]);
+            assert_near(
+                &shutter.transform(time).point(&p),
+                &motion.transform(time).point(&p),
+            );
+        }
+    }
+}
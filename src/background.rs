@@ -0,0 +1,171 @@
+use crate::color::Color;
+use crate::ray::Ray;
+use crate::utl;
+use crate::vec3::Vec3;
+
+/// radiance arriving from infinitely far away along rays that hit nothing
+pub trait Background: Send + Sync {
+    fn color(&self, ray: &Ray) -> Color;
+
+    /// direction towards the background and its density per solid angle,
+    /// None if the background is not sampled directly
+    fn sample(&self, _random: &mut utl::Random) -> Option<(Vec3, f64)> {
+        None
+    }
+
+    /// density per solid angle of `sample` returning `direction`
+    fn pdf(&self, _direction: &Vec3) -> f64 {
+        0.0
+    }
+}
+
+/// the same color in every direction (black for scenes lit only by emissive objects)
+pub struct SolidBackground {
+    pub color: Color,
+}
+
+impl SolidBackground {
+    pub fn new(color: &Color) -> Self {
+        Self {
+            color: color.clone(),
+        }
+    }
+}
+
+impl Background for SolidBackground {
+    fn color(&self, _ray: &Ray) -> Color {
+        self.color.clone()
+    }
+}
+
+/// vertical lerp from `bottom` (looking down) to `top` (looking up)
+pub struct GradientBackground {
+    pub bottom: Color,
+    pub top: Color,
+}
+
+impl Default for GradientBackground {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GradientBackground {
+    /// white to blue sky
+    pub fn new() -> Self {
+        Self::new_by_colors(
+            &Color::from_slice([1.0, 1.0, 1.0]),
+            &Color::from_slice([0.5, 0.7, 1.0]),
+        )
+    }
+
+    pub fn new_by_colors(bottom: &Color, top: &Color) -> Self {
+        Self {
+            bottom: bottom.clone(),
+            top: top.clone(),
+        }
+    }
+}
+
+impl Background for GradientBackground {
+    fn color(&self, ray: &Ray) -> Color {
+        let unit_direction = Vec3::new_unit_vec(ray.direction().clone());
+        let a = 0.5 * (unit_direction.y() + 1.0);
+
+        (1.0 - a) * self.bottom.clone() + a * self.top.clone()
+    }
+}
+
+/// procedural daylight sky: horizon to zenith gradient, ground and a sun disk
+pub struct SkyBackground {
+    /// direction towards the sun
+    pub sun_direction: Vec3,
+    /// radiance of the sun disk
+    pub sun_color: Color,
+    /// angular radius of the sun disk in degrees
+    pub sun_angular_radius: f64,
+    pub zenith_color: Color,
+    pub horizon_color: Color,
+    /// color below the horizon
+    pub ground_color: Color,
+}
+
+impl Default for SkyBackground {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SkyBackground {
+    /// afternoon sky with the sun 45 degrees above the horizon
+    pub fn new() -> Self {
+        Self {
+            sun_direction: Vec3::new_unit_vec(Vec3::from_slice([1.0, 1.0, -1.0])),
+            sun_color: Color::from_slice([20.0, 18.0, 15.0]),
+            sun_angular_radius: 2.0,
+            zenith_color: Color::from_slice([0.15, 0.35, 0.85]),
+            horizon_color: Color::from_slice([0.75, 0.85, 1.0]),
+            ground_color: Color::from_slice([0.35, 0.3, 0.25]),
+        }
+    }
+}
+
+impl Background for SkyBackground {
+    fn color(&self, ray: &Ray) -> Color {
+        let unit_direction = Vec3::new_unit_vec(ray.direction().clone());
+        let elevation = unit_direction.y();
+
+        let mut color = if elevation < 0.0 {
+            // short blend so that the horizon has no hard edge
+            let a = (-elevation * 20.0).min(1.0);
+            (1.0 - a) * self.horizon_color.clone() + a * self.ground_color.clone()
+        } else {
+            let a = elevation.sqrt();
+            (1.0 - a) * self.horizon_color.clone() + a * self.zenith_color.clone()
+        };
+
+        let sun_direction = Vec3::new_unit_vec(self.sun_direction.clone());
+        if unit_direction.dot(&sun_direction) >= self.sun_angular_radius.to_radians().cos() {
+            color += self.sun_color.clone();
+        }
+
+        color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Point3;
+
+    fn ray(direction: [f64; 3]) -> Ray {
+        Ray::from_origin_dir(&Point3::new(), &Vec3::from_slice(direction))
+    }
+
+    #[test]
+    fn test_gradient_background() {
+        let background = GradientBackground::new();
+        assert_eq!(background.color(&ray([0.0, 1.0, 0.0])).e, [0.5, 0.7, 1.0]);
+        assert_eq!(background.color(&ray([0.0, -2.0, 0.0])).e, [1.0, 1.0, 1.0]);
+        assert_eq!(
+            background.color(&ray([0.0, 0.0, -1.0])).e,
+            [0.75, 0.85, 1.0]
+        );
+
+        let solid = SolidBackground::new(&Color::new());
+        assert_eq!(solid.color(&ray([0.3, 0.2, 0.1])).e, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_sky_background() {
+        let sky = SkyBackground::new();
+        let zenith = sky.color(&ray([0.0, 1.0, 0.0]));
+        assert!((zenith - sky.zenith_color.clone()).norm() < 1e-12);
+        assert_eq!(sky.color(&ray([1.0, -1.0, 0.0])).e, sky.ground_color.e);
+
+        let sun = sky.color(&ray(sky.sun_direction.e));
+        assert!(sun.x() > sky.sun_color.x());
+        let away = sky.color(&ray([-1.0, 1.0, 1.0]));
+        assert!(away.x() < 1.0);
+    }
+}
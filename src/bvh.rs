@@ -1,140 +1,448 @@
 use crate::aabb::AaBb;
 use crate::hittable_list_aabb::HittableListAaBb;
-use crate::hittable_material::HittableAaBb;
+use crate::hittable_material::{HitRecordMat, HittableAaBb, HittableMat};
 use crate::interval::Interval;
+use crate::ray::Ray;
 use crate::utl;
-use std::cell::RefCell;
+use crate::vec3::Point3;
 use std::cmp::Ordering;
-use std::rc::Rc;
+use std::sync::Arc;
 
-pub struct BvhNode<T> {
-    left: Option<Rc<RefCell<T>>>,
-    right: Option<Rc<RefCell<T>>>,
+/// bounding volume hierarchy
+///
+/// children are either leaf primitives or other `BvhNode`s,
+/// so they are held as `dyn HittableAaBb`
+pub struct BvhNode {
+    left: Arc<dyn HittableAaBb>,
+    right: Arc<dyn HittableAaBb>,
     bbox: AaBb,
+    axis: usize,
 }
 
-impl<T: HittableAaBb> BvhNode<T> {
-    pub fn new(mut list: HittableListAaBb<T>) -> Self {
-        let n = list.objects.len();
-        Self::new_by_objects(&mut list.objects, 0, n)
+/// how a set of primitives is divided into two children
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitMethod {
+    /// binned Surface Area Heuristic
+    Sah,
+    /// split at the midpoint of the longest centroid axis
+    Middle,
+    /// split into two halves with the same number of primitives along the longest centroid axis
+    EqualCounts,
+}
+
+/// options for building `BvhNode`
+#[derive(Debug, Clone)]
+pub struct BvhOptions {
+    pub split_method: SplitMethod,
+    /// maximum number of primitives stored in one leaf
+    pub max_leaf_size: usize,
+    /// number of buckets used by `SplitMethod::Sah`
+    pub sah_buckets: usize,
+    /// relative cost of traversing an interior node (used by `SplitMethod::Sah`)
+    pub traversal_cost: f64,
+    /// relative cost of intersecting a primitive (used by `SplitMethod::Sah`)
+    pub intersection_cost: f64,
+}
+
+impl Default for BvhOptions {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    pub fn new_by_objects(objects: &mut Vec<Rc<RefCell<T>>>, start: usize, end: usize) -> Self {
-        let mut bvhnode = Self {
-            left: None,
-            right: None,
-            bbox: AaBb::new(),
-        };
+impl BvhOptions {
+    pub fn new() -> Self {
+        Self {
+            split_method: SplitMethod::Sah,
+            max_leaf_size: 4,
+            sah_buckets: 12,
+            traversal_cost: 0.125,
+            intersection_cost: 1.0,
+        }
+    }
+}
 
-        let axis = utl::random_i32(0.0, 2.0);
-        let comparator = match axis {
-            0 => Self::box_x_compare,
-            1 => Self::box_y_compare,
-            _ => Self::box_z_compare,
-        };
+/// primitive with cached bounding box and centroid during the build
+///
+/// `object` is the primitive itself or an index into a buffer of primitives
+pub(crate) struct BuildPrimitive<T = Arc<dyn HittableAaBb>> {
+    pub(crate) object: T,
+    pub(crate) bbox: AaBb,
+    centroid: Point3,
+}
 
-        let object_span = end - start;
-        match object_span {
-            1 => {
-                bvhnode.left = Some(objects[start].clone());
-                bvhnode.right = Some(objects[start].clone());
-            }
-            2 => {
-                bvhnode.left = Some(objects[start].clone());
-                bvhnode.right = Some(objects[start + 1].clone());
+impl<T> BuildPrimitive<T> {
+    pub(crate) fn new(object: T, bbox: AaBb) -> Self {
+        let centroid = bbox.centroid();
+        Self {
+            object,
+            bbox,
+            centroid,
+        }
+    }
+}
+
+/// where to split a set of primitives sorted along `axis`
+pub(crate) struct SplitPlan {
+    pub(crate) bbox: AaBb,
+    pub(crate) axis: usize,
+    pub(crate) mid: usize,
+    /// SAH cost of the split (infinite when it is not evaluated)
+    cost: f64,
+}
+
+impl SplitPlan {
+    /// whether `n` primitives should be kept in one leaf instead of being split
+    pub(crate) fn prefers_leaf(&self, n: usize, options: &BvhOptions) -> bool {
+        let leaf_cost = options.intersection_cost * n as f64;
+        n <= options.max_leaf_size && leaf_cost <= self.cost
+    }
+}
+
+impl BvhNode {
+    /// build with default options (binned SAH)
+    pub fn new(list: HittableListAaBb<dyn HittableAaBb>) -> Self {
+        Self::new_with_options(list, &BvhOptions::new())
+    }
+
+    pub fn new_with_options(
+        list: HittableListAaBb<dyn HittableAaBb>,
+        options: &BvhOptions,
+    ) -> Self {
+        let mut primitives = build_primitives(list);
+
+        match primitives.len() {
+            0 => {
+                let empty: Arc<dyn HittableAaBb> =
+                    Arc::new(HittableListAaBb::<dyn HittableAaBb>::new());
+                Self {
+                    left: empty.clone(),
+                    right: empty,
+                    bbox: AaBb::new(),
+                    axis: 0,
+                }
             }
+            1 => Self {
+                left: primitives[0].object.clone(),
+                right: primitives[0].object.clone(),
+                bbox: primitives[0].bbox.clone(),
+                axis: 0,
+            },
             _ => {
-                objects[start..end].sort_by(|a, b| comparator(a, b).unwrap());
-
-                let mid = start + object_span / 2;
-                /*
-                bvhnode.left = Some(Rc::new(RefCell::new(Self::new_by_objects(
-                    objects, start, mid,
-                ))));
-                bvhnode.right = Some(Rc::new(RefCell::new(Self::new_by_objects(
-                    objects, mid, end,
-                ))));
-                */
+                let plan = plan_split(&mut primitives, options);
+                Self::new_by_plan(&mut primitives, &plan, options)
             }
         }
-        bvhnode.bbox = AaBb::new_by_two_aabb(
-            &bvhnode
-                .left
-                .clone()
-                .unwrap()
-                .as_ref()
-                .borrow()
-                .bounding_box(),
-            &bvhnode
-                .right
-                .clone()
-                .unwrap()
-                .as_ref()
-                .borrow()
-                .bounding_box(),
-        );
+    }
 
-        bvhnode
+    fn new_by_plan(
+        primitives: &mut [BuildPrimitive],
+        plan: &SplitPlan,
+        options: &BvhOptions,
+    ) -> Self {
+        let (left_primitives, right_primitives) = primitives.split_at_mut(plan.mid);
+
+        Self {
+            left: Self::build(left_primitives, options),
+            right: Self::build(right_primitives, options),
+            bbox: plan.bbox.clone(),
+            axis: plan.axis,
+        }
     }
 
-    fn box_compare(a: &Rc<RefCell<T>>, b: &Rc<RefCell<T>>, axis_inex: usize) -> Option<Ordering> {
-        let a_axis_interval = a
-            .as_ref()
-            .borrow()
-            .bounding_box()
-            .axis_interval(axis_inex)
-            .clone();
-        let b_axis_interval = b
-            .as_ref()
-            .borrow()
-            .bounding_box()
-            .axis_interval(axis_inex)
-            .clone();
+    /// returns a single primitive, a leaf list or an interior node
+    fn build(primitives: &mut [BuildPrimitive], options: &BvhOptions) -> Arc<dyn HittableAaBb> {
+        if primitives.len() == 1 {
+            return primitives[0].object.clone();
+        }
+
+        let plan = plan_split(primitives, options);
+        if plan.prefers_leaf(primitives.len(), options) {
+            let mut leaf: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+            for primitive in primitives.iter() {
+                leaf.add(primitive.object.clone());
+            }
+            return Arc::new(leaf);
+        }
 
-        a_axis_interval.min.partial_cmp(&b_axis_interval.min)
+        Arc::new(Self::new_by_plan(primitives, &plan, options))
     }
+}
+
+pub(crate) fn build_primitives(list: HittableListAaBb<dyn HittableAaBb>) -> Vec<BuildPrimitive> {
+    list.objects
+        .into_iter()
+        .map(|object| {
+            let bbox = object.bounding_box();
+            BuildPrimitive::new(object, bbox)
+        })
+        .collect()
+}
+
+/// sort `primitives` along the longest centroid axis and choose the split point
+pub(crate) fn plan_split<T>(
+    primitives: &mut [BuildPrimitive<T>],
+    options: &BvhOptions,
+) -> SplitPlan {
+    let (bbox, centroid_bounds) = primitives.iter().fold(
+        (AaBb::new(), AaBb::new()),
+        |(bbox, centroid_bounds), primitive| {
+            (
+                AaBb::new_by_two_aabb(&bbox, &primitive.bbox),
+                AaBb::new_by_two_aabb(
+                    &centroid_bounds,
+                    &AaBb::new_by_two_points(&primitive.centroid, &primitive.centroid),
+                ),
+            )
+        },
+    );
+    let axis = centroid_bounds.longest_axis();
+    let axis_interval = centroid_bounds.axis_interval(axis).clone();
 
-    fn box_x_compare(a: &Rc<RefCell<T>>, b: &Rc<RefCell<T>>) -> Option<Ordering> {
-        Self::box_compare(a, b, 0)
+    primitives.sort_by(|a, b| {
+        a.centroid[axis]
+            .partial_cmp(&b.centroid[axis])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let n = primitives.len();
+    let (mid, cost) = if axis_interval.size() <= 0.0 {
+        (n / 2, utl::constans::INFINITY)
+    } else {
+        match options.split_method {
+            SplitMethod::Sah => sah_split(primitives, &bbox, axis, &axis_interval, options),
+            SplitMethod::Middle => {
+                let p_mid = 0.5 * (axis_interval.min + axis_interval.max);
+                (
+                    primitives.partition_point(|primitive| primitive.centroid[axis] < p_mid),
+                    utl::constans::INFINITY,
+                )
+            }
+            SplitMethod::EqualCounts => (n / 2, utl::constans::INFINITY),
+        }
+    };
+    // fall back to equal counts when the split puts everything on one side
+    let mid = if mid == 0 || mid == n { n / 2 } else { mid };
+
+    SplitPlan {
+        bbox,
+        axis,
+        mid,
+        cost,
     }
+}
 
-    fn box_y_compare(a: &Rc<RefCell<T>>, b: &Rc<RefCell<T>>) -> Option<Ordering> {
-        Self::box_compare(a, b, 1)
+/// returns (number of primitives in the left child, cost) of the cheapest bucket boundary.
+/// `primitives` must be sorted by centroid along `axis`.
+fn sah_split<T>(
+    primitives: &[BuildPrimitive<T>],
+    bbox: &AaBb,
+    axis: usize,
+    axis_interval: &Interval,
+    options: &BvhOptions,
+) -> (usize, f64) {
+    let n_buckets = options.sah_buckets.max(2);
+    let bucket_index = |primitive: &BuildPrimitive<T>| {
+        let b = ((primitive.centroid[axis] - axis_interval.min) / axis_interval.size()
+            * n_buckets as f64) as usize;
+        b.min(n_buckets - 1)
+    };
+
+    let mut counts = vec![0usize; n_buckets];
+    let mut bounds = vec![AaBb::new(); n_buckets];
+    for primitive in primitives {
+        let b = bucket_index(primitive);
+        counts[b] += 1;
+        bounds[b] = AaBb::new_by_two_aabb(&bounds[b], &primitive.bbox);
+    }
+
+    // sweep from the right to get the area and count of every right side
+    let mut right_area = vec![0.0; n_buckets];
+    let mut right_count = vec![0usize; n_buckets];
+    let mut right_box = AaBb::new();
+    let mut count = 0;
+    for b in (1..n_buckets).rev() {
+        right_box = AaBb::new_by_two_aabb(&right_box, &bounds[b]);
+        count += counts[b];
+        right_area[b] = right_box.surface_area();
+        right_count[b] = count;
     }
 
-    fn box_z_compare(a: &Rc<RefCell<T>>, b: &Rc<RefCell<T>>) -> Option<Ordering> {
-        Self::box_compare(a, b, 2)
+    let total_area = bbox.surface_area();
+    let mut best = (primitives.len() / 2, utl::constans::INFINITY);
+    let mut left_box = AaBb::new();
+    let mut left_count = 0;
+    for b in 0..n_buckets - 1 {
+        left_box = AaBb::new_by_two_aabb(&left_box, &bounds[b]);
+        left_count += counts[b];
+        if left_count == 0 || right_count[b + 1] == 0 {
+            continue;
+        }
+
+        let cost = options.traversal_cost
+            + options.intersection_cost
+                * (left_count as f64 * left_box.surface_area()
+                    + right_count[b + 1] as f64 * right_area[b + 1])
+                / total_area;
+        if cost < best.1 {
+            best = (left_count, cost);
+        }
     }
+
+    best
 }
 
-impl<T: HittableAaBb> HittableAaBb for BvhNode<T> {
-    fn hit_aabb(
-        &self,
-        ray: &crate::ray::Ray,
-        ray_t: crate::interval::Interval,
-        rec: &mut crate::hittable_material::HitRecordMat,
-    ) -> bool {
+impl HittableAaBb for BvhNode {
+    fn hit_aabb(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
         if !self.bbox.hit(ray, ray_t.clone()) {
             return false;
         }
 
-        let hit_left =
-            self.left
-                .clone()
-                .unwrap()
-                .as_ref()
-                .borrow()
-                .hit_aabb(&ray, ray_t.clone(), rec);
-        let hit_right = self.left.clone().unwrap().as_ref().borrow().hit_aabb(
-            &ray,
-            Interval::new_by_value(ray_t.min, if hit_left { rec.t } else { ray_t.max }),
+        // children are sorted along `axis`, so visit the near one first
+        let (near, far) = if ray.direction()[self.axis] < 0.0 {
+            (&self.right, &self.left)
+        } else {
+            (&self.left, &self.right)
+        };
+
+        let hit_near = near.hit_aabb(ray, ray_t.clone(), rec);
+        let hit_far = far.hit_aabb(
+            ray,
+            Interval::new_by_value(ray_t.min, if hit_near { rec.t } else { ray_t.max }),
             rec,
         );
 
-        hit_left || hit_right
+        hit_near || hit_far
     }
 
     fn bounding_box(&self) -> AaBb {
         self.bbox.clone()
     }
 }
+
+impl HittableMat for BvhNode {
+    fn hit_mat(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        self.hit_aabb(ray, ray_t, rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aabb;
+    use crate::interval::consts;
+    use crate::sphere_aabb::SphereAaBb;
+    use crate::vec3::{Point3, Vec3};
+
+    fn random_world(random: &mut utl::Random) -> HittableListAaBb<dyn HittableAaBb> {
+        let mut world: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+        for a in -5..5 {
+            for b in -5..5 {
+                let center = Point3::from_slice([
+                    a as f64 + 0.9 * random.random_f64(),
+                    0.2,
+                    b as f64 + 0.9 * random.random_f64(),
+                ]);
+                world.add(Arc::new(SphereAaBb::new_stationary(&center, 0.2, None)));
+            }
+        }
+        world.add(Arc::new(SphereAaBb::new_stationary(
+            &Point3::from_slice([0.0, -1000.0, 0.0]),
+            1000.0,
+            None,
+        )));
+
+        world
+    }
+
+    fn random_rays(random: &mut utl::Random) -> Vec<Ray> {
+        let origin = Point3::from_slice([13.0, 2.0, 3.0]);
+        (0..1000)
+            .map(|_| {
+                let target = Point3::from_slice([
+                    random.random_f64_range(-6.0, 6.0),
+                    random.random_f64_range(-1.0, 1.0),
+                    random.random_f64_range(-6.0, 6.0),
+                ]);
+                Ray::from_origin_dir(&origin, &(target - origin.clone()))
+            })
+            .collect()
+    }
+
+    fn build(world: &HittableListAaBb<dyn HittableAaBb>, split_method: SplitMethod) -> BvhNode {
+        let mut bvh_world: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+        for object in world.objects.iter() {
+            bvh_world.add(object.clone());
+        }
+        let mut options = BvhOptions::new();
+        options.split_method = split_method;
+        BvhNode::new_with_options(bvh_world, &options)
+    }
+
+    /// hit results and the number of `AaBb::hit` calls for each ray
+    fn trace(bvh: &BvhNode, rays: &[Ray]) -> Vec<(Option<f64>, usize)> {
+        rays.iter()
+            .map(|ray| {
+                let mut rec = HitRecordMat::new();
+                let ray_t = Interval::new_by_value(0.001, utl::constans::INFINITY);
+                let before = aabb::HIT_COUNT.with(|count| count.get());
+                let hit = bvh.hit_aabb(ray, ray_t, &mut rec);
+                let calls = aabb::HIT_COUNT.with(|count| count.get()) - before;
+                (hit.then_some(rec.t), calls)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bvh_hit_same_as_list() {
+        let mut random = utl::Random::new_by_seed(2);
+        let world = random_world(&mut random);
+        let rays = random_rays(&mut random);
+
+        for split_method in [
+            SplitMethod::Sah,
+            SplitMethod::Middle,
+            SplitMethod::EqualCounts,
+        ] {
+            let bvh = build(&world, split_method);
+            for (ray, (hit_bvh, _)) in rays.iter().zip(trace(&bvh, &rays)) {
+                let mut rec_list = HitRecordMat::new();
+                let ray_t = Interval::new_by_value(0.001, utl::constans::INFINITY);
+                let hit_list = world.hit_aabb(ray, ray_t, &mut rec_list);
+
+                assert_eq!(hit_list, hit_bvh.is_some());
+                if let Some(t) = hit_bvh {
+                    assert!((rec_list.t - t).abs() < 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bvh_reproducible_and_sah_cheaper() {
+        let mut random = utl::Random::new_by_seed(3);
+        let world = random_world(&mut random);
+        let rays = random_rays(&mut random);
+
+        let mut total_calls = vec![];
+        for split_method in [SplitMethod::Sah, SplitMethod::EqualCounts] {
+            // the same input always gives the same tree, so the same work per ray
+            let traced = trace(&build(&world, split_method), &rays);
+            assert_eq!(traced, trace(&build(&world, split_method), &rays));
+            total_calls.push(traced.iter().map(|(_, calls)| calls).sum::<usize>());
+        }
+
+        // SAH against the median split
+        let (sah, median) = (total_calls[0], total_calls[1]);
+        assert!((sah as f64) < 0.9 * median as f64, "{sah} {median}");
+    }
+
+    #[test]
+    fn test_empty_bvh() {
+        let bvh = BvhNode::new(HittableListAaBb::new());
+        let ray = Ray::from_origin_dir(&Point3::new(), &Vec3::from_slice([0.0, 0.0, -1.0]));
+        let mut rec = HitRecordMat::new();
+
+        assert!(!bvh.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::aabb::AaBb;
+use crate::bvh::{self, BuildPrimitive, BvhOptions};
+use crate::hittable_list_aabb::HittableListAaBb;
+use crate::hittable_material::{HitRecordMat, HittableAaBb, HittableMat};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use std::sync::Arc;
+
+/// node of `LinearBvh`
+///
+/// leaf: `n_primitives > 0` and `offset` is the index of the first primitive.
+/// interior: `n_primitives == 0`, the first child is the next node and
+/// `offset` is the index of the second child.
+pub(crate) struct LinearBvhNode {
+    bbox: AaBb,
+    offset: usize,
+    n_primitives: usize,
+    axis: usize,
+}
+
+/// bounding volume hierarchy flattened into arrays (depth-first order)
+pub struct LinearBvh {
+    nodes: Vec<LinearBvhNode>,
+    primitives: Vec<Arc<dyn HittableAaBb>>,
+}
+
+impl LinearBvh {
+    /// build with default options (binned SAH)
+    pub fn new(list: HittableListAaBb<dyn HittableAaBb>) -> Self {
+        Self::new_with_options(list, &BvhOptions::new())
+    }
+
+    pub fn new_with_options(
+        list: HittableListAaBb<dyn HittableAaBb>,
+        options: &BvhOptions,
+    ) -> Self {
+        let (nodes, primitives) = build_nodes(bvh::build_primitives(list), options);
+
+        Self { nodes, primitives }
+    }
+}
+
+/// build the flattened nodes and the primitives in the order the leaves refer to them
+pub(crate) fn build_nodes<T>(
+    mut build_primitives: Vec<BuildPrimitive<T>>,
+    options: &BvhOptions,
+) -> (Vec<LinearBvhNode>, Vec<T>) {
+    let mut nodes = Vec::with_capacity(2 * build_primitives.len());
+    if !build_primitives.is_empty() {
+        flatten(&mut nodes, &mut build_primitives, 0, options);
+    }
+
+    // the build sorts the primitives in place, so the leaves are consecutive ranges
+    let primitives = build_primitives
+        .into_iter()
+        .map(|primitive| primitive.object)
+        .collect();
+
+    (nodes, primitives)
+}
+
+/// append the subtree of `primitives`, whose first primitive is at `offset`
+/// of all primitives, and return the index of its root node
+fn flatten<T>(
+    nodes: &mut Vec<LinearBvhNode>,
+    primitives: &mut [BuildPrimitive<T>],
+    offset: usize,
+    options: &BvhOptions,
+) -> usize {
+    let index = nodes.len();
+
+    if primitives.len() == 1 {
+        nodes.push(LinearBvhNode {
+            bbox: primitives[0].bbox.clone(),
+            offset,
+            n_primitives: 1,
+            axis: 0,
+        });
+        return index;
+    }
+
+    let plan = bvh::plan_split(primitives, options);
+    if plan.prefers_leaf(primitives.len(), options) {
+        nodes.push(LinearBvhNode {
+            bbox: plan.bbox,
+            offset,
+            n_primitives: primitives.len(),
+            axis: plan.axis,
+        });
+        return index;
+    }
+
+    nodes.push(LinearBvhNode {
+        bbox: plan.bbox,
+        offset: 0,
+        n_primitives: 0,
+        axis: plan.axis,
+    });
+    let (left_primitives, right_primitives) = primitives.split_at_mut(plan.mid);
+    flatten(nodes, left_primitives, offset, options);
+    nodes[index].offset = flatten(nodes, right_primitives, offset + plan.mid, options);
+
+    index
+}
+
+/// visit the nodes front to back, `hit_primitive(k, ray_t)` returns the ray parameter
+/// of the hit with primitive `k` in `ray_t`
+pub(crate) fn hit_nodes(
+    nodes: &[LinearBvhNode],
+    ray: &Ray,
+    ray_t: Interval,
+    mut hit_primitive: impl FnMut(usize, Interval) -> Option<f64>,
+) -> bool {
+    if nodes.is_empty() {
+        return false;
+    }
+
+    let dir_is_neg = [
+        ray.direction()[0] < 0.0,
+        ray.direction()[1] < 0.0,
+        ray.direction()[2] < 0.0,
+    ];
+    let mut hit_anything = false;
+    let mut closest_so_far = ray_t.max;
+    let mut to_visit = Vec::with_capacity(64);
+    let mut current = 0;
+
+    loop {
+        let node = &nodes[current];
+        if node
+            .bbox
+            .hit(ray, Interval::new_by_value(ray_t.min, closest_so_far))
+        {
+            if node.n_primitives > 0 {
+                for k in node.offset..node.offset + node.n_primitives {
+                    if let Some(t) =
+                        hit_primitive(k, Interval::new_by_value(ray_t.min, closest_so_far))
+                    {
+                        hit_anything = true;
+                        closest_so_far = t;
+                    }
+                }
+            } else if dir_is_neg[node.axis] {
+                // visit the near (second) child first
+                to_visit.push(current + 1);
+                current = node.offset;
+                continue;
+            } else {
+                to_visit.push(node.offset);
+                current += 1;
+                continue;
+            }
+        }
+
+        match to_visit.pop() {
+            Some(next) => current = next,
+            None => break,
+        }
+    }
+
+    hit_anything
+}
+
+/// bounding box of the root node
+pub(crate) fn nodes_bounding_box(nodes: &[LinearBvhNode]) -> AaBb {
+    match nodes.first() {
+        Some(root) => root.bbox.clone(),
+        None => AaBb::new(),
+    }
+}
+
+impl HittableAaBb for LinearBvh {
+    fn hit_aabb(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        hit_nodes(&self.nodes, ray, ray_t, |k, ray_t| {
+            self.primitives[k]
+                .hit_aabb(ray, ray_t, rec)
+                .then_some(rec.t)
+        })
+    }
+
+    fn bounding_box(&self) -> AaBb {
+        nodes_bounding_box(&self.nodes)
+    }
+}
+
+impl HittableMat for LinearBvh {
+    fn hit_mat(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        self.hit_aabb(ray, ray_t, rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bvh::SplitMethod;
+    use crate::sphere_aabb::SphereAaBb;
+    use crate::utl;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn test_linear_bvh_hit_same_as_list() {
+        let mut random = utl::Random::new_by_seed(3);
+        let mut list: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+        for a in -5..5 {
+            for b in -5..5 {
+                let center = Point3::from_slice([
+                    a as f64 + 0.9 * random.random_f64(),
+                    0.2 + random.random_f64(),
+                    b as f64 + 0.9 * random.random_f64(),
+                ]);
+                list.add(Arc::new(SphereAaBb::new_stationary(&center, 0.3, None)));
+            }
+        }
+
+        for split_method in [
+            SplitMethod::Sah,
+            SplitMethod::Middle,
+            SplitMethod::EqualCounts,
+        ] {
+            let mut bvh_list: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+            for object in list.objects.iter() {
+                bvh_list.add(object.clone());
+            }
+            let mut options = BvhOptions::new();
+            options.split_method = split_method;
+            let linear_bvh = LinearBvh::new_with_options(bvh_list, &options);
+            assert_eq!(linear_bvh.primitives.len(), list.objects.len());
+
+            let origin = Point3::from_slice([13.0, 2.0, 3.0]);
+            for _ in 0..1000 {
+                let target = Point3::from_slice([
+                    random.random_f64_range(-6.0, 6.0),
+                    random.random_f64_range(-1.0, 2.0),
+                    random.random_f64_range(-6.0, 6.0),
+                ]);
+                let ray = Ray::from_origin_dir(&origin, &(target - origin.clone()));
+
+                let mut rec_list = HitRecordMat::new();
+                let mut rec_bvh = HitRecordMat::new();
+                let ray_t = Interval::new_by_value(0.001, utl::constans::INFINITY);
+                let hit_list = list.hit_aabb(&ray, ray_t.clone(), &mut rec_list);
+                let hit_bvh = linear_bvh.hit_aabb(&ray, ray_t, &mut rec_bvh);
+
+                assert_eq!(hit_list, hit_bvh);
+                if hit_list {
+                    assert!((rec_list.t - rec_bvh.t).abs() < 1e-12);
+                }
+            }
+        }
+    }
+}
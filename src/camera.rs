@@ -1,14 +1,104 @@
-use crate::color::{write_color, write_color_gamma, Color};
-use crate::hittable::HitRecord;
-use crate::hittable::HittableV2;
-use crate::hittable_material::{HitRecordMat, HittableMat};
-use crate::interval::Interval;
+use crate::background::{Background, GradientBackground};
+use crate::camera_path::{CameraKeyframe, CameraPath};
+use crate::color::Color;
+use crate::framebuffer::FrameBuffer;
+use crate::integrator::Integrator;
 use crate::ray::Ray;
 use crate::utl;
 use crate::vec3::{Point3, Vec3};
+use std::borrow::Cow;
 use std::error::Error;
-use std::fs::File;
-use std::io::Write;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// projection model of the camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lens {
+    /// every ray starts from `look_from`, the focal length is |look_from - look_at|
+    Pinhole,
+    /// rays start from a disk of `defocus_angle` and are focused at `focus_dist`
+    ThinLens,
+}
+
+/// how much light the shutter lets through while it opens and closes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShutterCurve {
+    /// fully open from `shutter_open` to `shutter_close`
+    Box,
+    /// efficiency rises linearly over the first `opening` and falls over the last `closing`
+    /// fraction of the shutter interval (`opening + closing <= 1`, see `trapezoid`)
+    Trapezoid { opening: f64, closing: f64 },
+}
+
+impl ShutterCurve {
+    /// error if a fraction is negative or `opening + closing > 1`
+    pub fn trapezoid(opening: f64, closing: f64) -> Result<Self, Box<dyn Error>> {
+        if !(opening >= 0.0 && closing >= 0.0 && opening + closing <= 1.0) {
+            return Err(format!(
+                "invalid shutter trapezoid: opening {opening}, closing {closing} \
+                 (must be non-negative with a sum of at most 1)"
+            )
+            .into());
+        }
+
+        Ok(Self::Trapezoid { opening, closing })
+    }
+
+    /// fractions of a trapezoid clamped to a valid one
+    fn ramps(opening: f64, closing: f64) -> (f64, f64) {
+        // NaN becomes 0 too
+        let opening = if opening > 0.0 { opening.min(1.0) } else { 0.0 };
+        let closing = if closing > 0.0 {
+            closing.min(1.0 - opening)
+        } else {
+            0.0
+        };
+        (opening, closing)
+    }
+
+    /// efficiency (0..1) at `s` (0: the shutter opens, 1: it is closed)
+    pub fn efficiency(&self, s: f64) -> f64 {
+        if !(0.0..=1.0).contains(&s) {
+            return 0.0;
+        }
+        match *self {
+            Self::Box => 1.0,
+            Self::Trapezoid { opening, closing } => {
+                let (opening, closing) = Self::ramps(opening, closing);
+                if s < opening {
+                    s / opening
+                } else if s > 1.0 - closing {
+                    (1.0 - s) / closing
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+
+    /// map `xi` uniform in [0, 1) to `s` distributed by the efficiency
+    pub fn sample(&self, xi: f64) -> f64 {
+        match *self {
+            Self::Box => xi,
+            Self::Trapezoid { opening, closing } => {
+                let (opening, closing) = Self::ramps(opening, closing);
+                let (ramp_up, flat) = (0.5 * opening, 1.0 - opening - closing);
+                let x = xi * (ramp_up + flat + 0.5 * closing);
+                if x < ramp_up {
+                    (2.0 * x * opening).sqrt()
+                } else if x < ramp_up + flat {
+                    opening + (x - ramp_up)
+                } else {
+                    // the area left to the end
+                    let rest = ramp_up + flat + 0.5 * closing - x;
+                    1.0 - (2.0 * rest * closing).sqrt()
+                }
+            }
+        }
+    }
+}
 
 pub struct Camera {
     pub aspect_ratio: f64,
@@ -19,22 +109,60 @@ pub struct Camera {
     pub look_from: Point3,
     pub look_at: Point3,
     pub v_up: Point3,
+    pub lens: Lens,
     pub defocus_angle: f64,
     pub focus_dist: f64,
-    pixel_samples_scale: f64,
+    /// jitter the samples in the pixel square (false: always sample the pixel center)
+    pub pixel_jitter: bool,
+    /// seed of the random numbers used in rendering
+    pub seed: u64,
+    /// width and height of one tile rendered by a thread
+    pub tile_size: i32,
+    /// number of rendering threads (0: all available cores)
+    pub n_threads: usize,
+    /// seen by rays that hit nothing
+    pub background: Arc<dyn Background>,
+    /// ray times are in [shutter_open, shutter_close), in the units of the keyframe times
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    pub shutter_curve: ShutterCurve,
+    /// keyframed `look_from`, `look_at`, `v_up`, `vfov` and `focus_dist` by the ray time
+    /// (None: the fixed values above)
+    pub path: Option<CameraPath>,
     image_height: i32,
+    view: View,
+}
+
+/// where the rays start and which pixels they go through for one camera placement
+#[derive(Clone)]
+struct View {
     center: Point3,
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
-    random: utl::Random,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
 }
 
+impl View {
+    fn new() -> Self {
+        Self {
+            center: Point3::new(),
+            pixel00_loc: Point3::new(),
+            pixel_delta_u: Vec3::new(),
+            pixel_delta_v: Vec3::new(),
+            defocus_disk_u: Vec3::new(),
+            defocus_disk_v: Vec3::new(),
+        }
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Camera {
     pub fn new() -> Self {
         Self {
@@ -46,306 +174,148 @@ impl Camera {
             look_from: Point3::new(),
             look_at: Point3::from_slice([0.0, 0.0, -1.0]),
             v_up: Point3::from_slice([0.0, 1.0, 0.0]),
+            lens: Lens::ThinLens,
             defocus_angle: 0.0,
             focus_dist: 10.0,
-            pixel_samples_scale: 0.0,
+            pixel_jitter: true,
+            seed: 0,
+            tile_size: 16,
+            n_threads: 0,
+            background: Arc::new(GradientBackground::new()),
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            shutter_curve: ShutterCurve::Box,
+            path: None,
             image_height: 0,
-            center: Point3::new(),
-            pixel00_loc: Point3::new(),
-            pixel_delta_u: Vec3::new(),
-            pixel_delta_v: Vec3::new(),
-            random: utl::Random::new(),
-            u: Vec3::new(),
-            v: Vec3::new(),
-            w: Vec3::new(),
-            defocus_disk_u: Vec3::new(),
-            defocus_disk_v: Vec3::new(),
+            view: View::new(),
         }
     }
 
-    pub fn render<T: HittableV2>(
-        &mut self,
-        world: &T,
-        file_name: &str,
-    ) -> Result<(), Box<dyn Error>> {
-        self.initialize();
-
-        let mut file = File::create(file_name)?;
-        let header = format!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-        std::writeln!(&mut file, "{header}")?;
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let pixel_center = self.pixel00_loc.clone()
-                    + (i as f64 * self.pixel_delta_u.clone())
-                    + (j as f64 * self.pixel_delta_v.clone());
-                let ray_direction = pixel_center - self.center.clone();
-                let ray = Ray::from_origin_dir(&self.center, &ray_direction);
-
-                let pixel_color = Self::ray_color(&ray, world);
-                write_color(&mut file, &pixel_color)?;
-            }
-        }
-        eprintln!("\rDone.   ");
-
-        Ok(())
-    }
-
-    pub fn render_v2<T: HittableV2>(
-        &mut self,
-        world: &T,
-        file_name: &str,
-    ) -> Result<(), Box<dyn Error>> {
-        self.initialize();
-
-        let mut file = File::create(file_name)?;
-        let header = format!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-        std::writeln!(&mut file, "{header}")?;
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new();
-                for _sample in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += Self::ray_color(&ray, world);
-                }
-                pixel_color *= self.pixel_samples_scale;
-                write_color(&mut file, &pixel_color)?;
-                pixel_color /= self.pixel_samples_scale;
-            }
-        }
-        eprintln!("\rDone.   ");
-
-        Ok(())
-    }
-
-    pub fn render_diffuse<T: HittableV2>(
-        &mut self,
-        world: &T,
-        file_name: &str,
-    ) -> Result<(), Box<dyn Error>> {
-        self.initialize();
-
-        let mut file = File::create(file_name)?;
-        let header = format!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-        std::writeln!(&mut file, "{header}")?;
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new();
-                for _sample in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += Self::ray_color_diffuse(&ray, world);
-                }
-                pixel_color *= self.pixel_samples_scale;
-                write_color(&mut file, &pixel_color)?;
-                pixel_color /= self.pixel_samples_scale;
-            }
-        }
-        eprintln!("\rDone.   ");
-
-        Ok(())
+    /// open the shutter at the start of `frame` for `shutter_angle` degrees
+    /// (360: the whole frame, 180: half of it) at `fps` frames per time unit
+    pub fn set_shutter_for_frame(&mut self, frame: i32, fps: f64, shutter_angle: f64) {
+        self.shutter_open = frame as f64 / fps;
+        self.shutter_close = self.shutter_open + shutter_angle / 360.0 / fps;
     }
 
-    pub fn render_diffuse_max_depth<T: HittableV2>(
+    /// render the frames of an animation with the shutter set by `set_shutter_for_frame`,
+    /// `on_frame` gets every finished frame (e.g. to write it)
+    pub fn render_sequence<W: Sync + ?Sized, I: Integrator<W>>(
         &mut self,
-        world: &T,
-        file_name: &str,
+        world: &W,
+        integrator: &I,
+        frames: Range<i32>,
+        fps: f64,
+        shutter_angle: f64,
+        mut on_frame: impl FnMut(i32, FrameBuffer) -> Result<(), Box<dyn Error>>,
     ) -> Result<(), Box<dyn Error>> {
-        self.initialize();
-
-        let mut file = File::create(file_name)?;
-        let header = format!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-        std::writeln!(&mut file, "{header}")?;
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new();
-                for _sample in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += Self::ray_color_diffuse_max_depth(&ray, self.max_depth, world);
-                }
-                pixel_color *= self.pixel_samples_scale;
-                write_color(&mut file, &pixel_color)?;
-                pixel_color /= self.pixel_samples_scale;
-            }
-        }
-        eprintln!("\rDone.   ");
-
-        Ok(())
-    }
-
-    pub fn render_lambertian<T: HittableV2>(
-        &mut self,
-        world: &T,
-        file_name: &str,
-    ) -> Result<(), Box<dyn Error>> {
-        self.initialize();
-
-        let mut file = File::create(file_name)?;
-        let header = format!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-        std::writeln!(&mut file, "{header}")?;
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new();
-                for _sample in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += Self::ray_color_lambertian(&ray, self.max_depth, world);
-                }
-                pixel_color *= self.pixel_samples_scale;
-                write_color(&mut file, &pixel_color)?;
-                pixel_color /= self.pixel_samples_scale;
-            }
-        }
-        eprintln!("\rDone.   ");
-
-        Ok(())
-    }
-
-    pub fn render_gamma<T: HittableV2>(
-        &mut self,
-        gamma: f64,
-        world: &T,
-        file_name: &str,
-    ) -> Result<(), Box<dyn Error>> {
-        self.initialize();
-
-        let mut file = File::create(file_name)?;
-        let header = format!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-        std::writeln!(&mut file, "{header}")?;
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new();
-                for _sample in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += Self::ray_color_lambertian(&ray, self.max_depth, world);
-                }
-                pixel_color *= self.pixel_samples_scale;
-                write_color_gamma(gamma, &mut file, &pixel_color)?;
-                pixel_color /= self.pixel_samples_scale;
-            }
+        for frame in frames {
+            self.set_shutter_for_frame(frame, fps, shutter_angle);
+            on_frame(frame, self.render(world, integrator))?;
         }
-        eprintln!("\rDone.   ");
 
         Ok(())
     }
 
-    pub fn render_material<T: HittableMat>(
+    /// render `world` with `integrator` into a new framebuffer
+    pub fn render<W: Sync + ?Sized, I: Integrator<W>>(
         &mut self,
-        gamma: f64,
-        world: &T,
-        file_name: &str,
-    ) -> Result<(), Box<dyn Error>> {
+        world: &W,
+        integrator: &I,
+    ) -> FrameBuffer {
         self.initialize();
 
-        let mut file = File::create(file_name)?;
-        let header = format!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-        std::writeln!(&mut file, "{header}")?;
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new();
-                for _sample in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += Self::ray_color_material(&ray, self.max_depth, world);
-                }
-                pixel_color *= self.pixel_samples_scale;
-                write_color_gamma(gamma, &mut file, &pixel_color)?;
-                pixel_color /= self.pixel_samples_scale;
-            }
-        }
+        let framebuffer = self.render_tiles(world, integrator);
         eprintln!("\rDone.   ");
 
-        Ok(())
+        framebuffer
     }
 
-    pub fn render_view<T: HittableMat>(
-        &mut self,
-        gamma: f64,
-        world: &T,
-        file_name: &str,
-    ) -> Result<(), Box<dyn Error>> {
-        self.initialize_view();
-
-        let mut file = File::create(file_name)?;
-        let header = format!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-        std::writeln!(&mut file, "{header}")?;
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new();
-                for _sample in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += Self::ray_color_material(&ray, self.max_depth, world);
-                }
-                pixel_color *= self.pixel_samples_scale;
-                write_color_gamma(gamma, &mut file, &pixel_color)?;
-                pixel_color /= self.pixel_samples_scale;
-            }
+    /// render all tiles with multiple threads
+    fn render_tiles<W: Sync + ?Sized, I: Integrator<W>>(
+        &self,
+        world: &W,
+        integrator: &I,
+    ) -> FrameBuffer {
+        let (width, height) = (self.image_width as usize, self.image_height as usize);
+        let tile_size = self.tile_size.max(1) as usize;
+        let (n_tiles_x, n_tiles_y) = (width.div_ceil(tile_size), height.div_ceil(tile_size));
+        let n_tiles = n_tiles_x * n_tiles_y;
+        let n_threads = if self.n_threads == 0 {
+            thread::available_parallelism().map_or(1, |n| n.get())
+        } else {
+            self.n_threads
         }
-        eprintln!("\rDone.   ");
-
-        Ok(())
-    }
-
-    pub fn render_defocus<T: HittableMat>(
-        &mut self,
-        gamma: f64,
-        world: &T,
-        file_name: &str,
-    ) -> Result<(), Box<dyn Error>> {
-        self.initialize_defocus();
-
-        let mut file = File::create(file_name)?;
-        let header = format!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-        std::writeln!(&mut file, "{header}")?;
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new();
-                for _sample in 0..self.samples_per_pixel {
-                    let ray = self.get_ray_defocus(i, j);
-                    pixel_color += Self::ray_color_material(&ray, self.max_depth, world);
-                }
-                pixel_color *= self.pixel_samples_scale;
-                write_color_gamma(gamma, &mut file, &pixel_color)?;
-                pixel_color /= self.pixel_samples_scale;
+        .min(n_tiles);
+
+        let next_tile = AtomicUsize::new(0);
+        let finished_tiles = AtomicUsize::new(0);
+        let framebuffer = Mutex::new(FrameBuffer::new(self.image_width, self.image_height));
+        thread::scope(|scope| {
+            for _ in 0..n_threads {
+                scope.spawn(|| loop {
+                    let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                    if tile >= n_tiles {
+                        break;
+                    }
+
+                    let (x_0, y_0) = (
+                        (tile % n_tiles_x) * tile_size,
+                        (tile / n_tiles_x) * tile_size,
+                    );
+                    let (x_1, y_1) = ((x_0 + tile_size).min(width), (y_0 + tile_size).min(height));
+                    let mut tile_pixels = Vec::with_capacity((x_1 - x_0) * (y_1 - y_0));
+                    for j in y_0..y_1 {
+                        for i in x_0..x_1 {
+                            tile_pixels
+                                .push(self.sample_pixel(i as i32, j as i32, world, integrator));
+                        }
+                    }
+
+                    let mut framebuffer = framebuffer.lock().unwrap();
+                    for (k, pixel_sum) in tile_pixels.into_iter().enumerate() {
+                        let (i, j) = (x_0 + k % (x_1 - x_0), y_0 + k / (x_1 - x_0));
+                        framebuffer.add_samples(
+                            i as i32,
+                            j as i32,
+                            &pixel_sum,
+                            self.samples_per_pixel.max(0) as u32,
+                        );
+                    }
+                    drop(framebuffer);
+
+                    // counted when merged, so 0 is shown after the slowest tile
+                    let finished = finished_tiles.fetch_add(1, Ordering::Relaxed) + 1;
+                    eprintln!("\rTiles remaining: {} ", n_tiles - finished);
+                });
             }
-        }
-        eprintln!("\rDone.   ");
+        });
 
-        Ok(())
+        framebuffer.into_inner().unwrap()
     }
 
-    pub fn render_motion_blur<T: HittableMat>(
-        &mut self,
-        gamma: f64,
-        world: &T,
-        file_name: &str,
-    ) -> Result<(), Box<dyn Error>> {
-        self.initialize_defocus();
-
-        let mut file = File::create(file_name)?;
-        let header = format!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-        std::writeln!(&mut file, "{header}")?;
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new();
-                for _sample in 0..self.samples_per_pixel {
-                    let ray = self.get_ray_motion_blur(i, j);
-                    pixel_color += Self::ray_color_material(&ray, self.max_depth, world);
-                }
-                pixel_color *= self.pixel_samples_scale;
-                write_color_gamma(gamma, &mut file, &pixel_color)?;
-                pixel_color /= self.pixel_samples_scale;
-            }
+    /// sum of the sample colors of pixel (i, j)
+    fn sample_pixel<W: ?Sized, I: Integrator<W>>(
+        &self,
+        i: i32,
+        j: i32,
+        world: &W,
+        integrator: &I,
+    ) -> Color {
+        let mut pixel_sum = Color::new();
+        for sample in 0..self.samples_per_pixel {
+            let mut random = utl::Random::new_for_sample(self.seed, i, j, sample);
+            let ray = self.get_ray(i, j, &mut random);
+            pixel_sum += integrator.ray_color(
+                &ray,
+                self.max_depth,
+                world,
+                self.background.as_ref(),
+                &mut random,
+            );
         }
-        eprintln!("\rDone.   ");
 
-        Ok(())
+        pixel_sum
     }
 
     fn initialize(&mut self) {
@@ -356,287 +326,270 @@ impl Camera {
         } else {
             self.image_height
         };
-        self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
-
-        // camera
-        self.center = Point3::from_slice([0.0, 0.0, 0.0]);
-        let focal_lenth = 1.0;
-        let theta = self.vfov.to_radians();
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * focal_lenth;
-        let viweport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
-
-        // calculate the vector across the horizontal and down the vertical viewport edge.
-        let viewport_u = Vec3::from_slice([viweport_width, 0.0, 0.0]);
-        let viewport_v = Vec3::from_slice([0.0, -viewport_height, 0.0]);
-
-        // calculate the horizontal. and vertical delta vectors from pixel to pixel.
-        self.pixel_delta_u = viewport_u.clone() / self.image_width as f64;
-        self.pixel_delta_v = viewport_v.clone() / self.image_height as f64;
 
-        // calculate the location of the upper left pixel
-        let viewport_upper_left = self.center.clone()
-            - Vec3::from_slice([0.0, 0.0, focal_lenth])
-            - viewport_u / 2.0
-            - viewport_v / 2.0;
-        self.pixel00_loc =
-            viewport_upper_left + 0.5 * (self.pixel_delta_u.clone() + self.pixel_delta_v.clone());
+        self.view = self.compute_view(&self.fixed_keyframe());
     }
 
-    fn initialize_view(&mut self) {
-        // calculate the image height (Its ensure that it's at leat 1)
-        self.image_height = (self.image_width as f64 / self.aspect_ratio) as i32;
-        self.image_height = if self.image_height < 1 {
-            1
-        } else {
-            self.image_height
-        };
-        self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
-
-        // camera
-        self.center = self.look_from.clone();
-        let focal_lenth = (self.look_from.clone() - self.look_at.clone()).norm();
-        let theta = self.vfov.to_radians();
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * focal_lenth;
-        let viweport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
-
-        self.w = Vec3::new_unit_vec(self.look_from.clone() - self.look_at.clone());
-        self.u = Vec3::new_unit_vec(self.v_up.cross(&self.w));
-        self.v = self.w.cross(&self.u);
-
-        // calculate the vector across the horizontal and down the vertical viewport edge.
-        let viewport_u = viweport_width * self.u.clone();
-        let viewport_v = -viewport_height * self.v.clone();
-
-        // calculate the horizontal. and vertical delta vectors from pixel to pixel.
-        self.pixel_delta_u = viewport_u.clone() / self.image_width as f64;
-        self.pixel_delta_v = viewport_v.clone() / self.image_height as f64;
-
-        // calculate the location of the upper left pixel
-        let viewport_upper_left = self.center.clone()
-            - (focal_lenth * self.w.clone())
-            - viewport_u / 2.0
-            - viewport_v / 2.0;
-        self.pixel00_loc =
-            viewport_upper_left + 0.5 * (self.pixel_delta_u.clone() + self.pixel_delta_v.clone());
+    /// the fixed placement of the camera fields
+    fn fixed_keyframe(&self) -> CameraKeyframe {
+        CameraKeyframe {
+            time: self.shutter_open,
+            look_from: self.look_from.clone(),
+            look_at: self.look_at.clone(),
+            v_up: self.v_up.clone(),
+            vfov: self.vfov,
+            focus_dist: self.focus_dist,
+        }
     }
 
-    fn initialize_defocus(&mut self) {
-        // calculate the image height (Its ensure that it's at leat 1)
-        self.image_height = (self.image_width as f64 / self.aspect_ratio) as i32;
-        self.image_height = if self.image_height < 1 {
-            1
-        } else {
-            self.image_height
+    fn compute_view(&self, keyframe: &CameraKeyframe) -> View {
+        let center = keyframe.look_from.clone();
+        let focal_lenth = match self.lens {
+            Lens::Pinhole => (keyframe.look_from.clone() - keyframe.look_at.clone()).norm(),
+            Lens::ThinLens => keyframe.focus_dist,
         };
-        self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
-
-        // camera
-        self.center = self.look_from.clone();
-        let theta = self.vfov.to_radians();
+        let theta = keyframe.vfov.to_radians();
         let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * self.focus_dist;
+        let viewport_height = 2.0 * h * focal_lenth;
         let viweport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
 
-        self.w = Vec3::new_unit_vec(self.look_from.clone() - self.look_at.clone());
-        self.u = Vec3::new_unit_vec(self.v_up.cross(&self.w));
-        self.v = self.w.cross(&self.u);
+        let w = Vec3::new_unit_vec(keyframe.look_from.clone() - keyframe.look_at.clone());
+        let u = Vec3::new_unit_vec(keyframe.v_up.cross(&w));
+        let v = w.cross(&u);
 
         // calculate the vector across the horizontal and down the vertical viewport edge.
-        let viewport_u = viweport_width * self.u.clone();
-        let viewport_v = -viewport_height * self.v.clone();
+        let viewport_u = viweport_width * u.clone();
+        let viewport_v = -viewport_height * v.clone();
 
         // calculate the horizontal. and vertical delta vectors from pixel to pixel.
-        self.pixel_delta_u = viewport_u.clone() / self.image_width as f64;
-        self.pixel_delta_v = viewport_v.clone() / self.image_height as f64;
+        let pixel_delta_u = viewport_u.clone() / self.image_width as f64;
+        let pixel_delta_v = viewport_v.clone() / self.image_height as f64;
 
         // calculate the location of the upper left pixel
-        let viewport_upper_left = self.center.clone()
-            - (self.focus_dist * self.w.clone())
-            - viewport_u / 2.0
-            - viewport_v / 2.0;
-        self.pixel00_loc =
-            viewport_upper_left + 0.5 * (self.pixel_delta_u.clone() + self.pixel_delta_v.clone());
+        let viewport_upper_left =
+            center.clone() - (focal_lenth * w) - viewport_u / 2.0 - viewport_v / 2.0;
+        let pixel00_loc =
+            viewport_upper_left + 0.5 * (pixel_delta_u.clone() + pixel_delta_v.clone());
 
         // calculate the camera defocus disk basis vectors
-        let defocus_radius = self.focus_dist * (self.defocus_angle.to_radians() / 2.0).tan();
-        self.defocus_disk_u = defocus_radius * self.u.clone();
-        self.defocus_disk_v = defocus_radius * self.v.clone();
-    }
-
-    fn get_ray(&mut self, i: i32, j: i32) -> Ray {
-        let offset = self.sample_square();
-        let pixel_sample = self.pixel00_loc.clone()
-            + (i as f64 + offset.x()) * self.pixel_delta_u.clone()
-            + (j as f64 + offset.y()) * self.pixel_delta_v.clone();
-        let ray_origin = self.center.clone();
-        let ray_direction = pixel_sample - ray_origin.clone();
-
-        Ray::from_origin_dir(&ray_origin, &ray_direction)
+        let defocus_radius = keyframe.focus_dist * (self.defocus_angle.to_radians() / 2.0).tan();
+
+        View {
+            center,
+            pixel00_loc,
+            pixel_delta_u,
+            pixel_delta_v,
+            defocus_disk_u: defocus_radius * u,
+            defocus_disk_v: defocus_radius * v,
+        }
     }
 
-    fn get_ray_defocus(&mut self, i: i32, j: i32) -> Ray {
-        let offset = self.sample_square();
-        let pixel_sample = self.pixel00_loc.clone()
-            + (i as f64 + offset.x()) * self.pixel_delta_u.clone()
-            + (j as f64 + offset.y()) * self.pixel_delta_v.clone();
-        let ray_origin = if self.defocus_angle < 0.0 {
-            self.center.clone()
+    fn get_ray(&self, i: i32, j: i32, random: &mut utl::Random) -> Ray {
+        let offset = if self.pixel_jitter {
+            Self::sample_square(random)
         } else {
-            self.defocus_disk_sample()
+            Vec3::new()
         };
-
-        let ray_direction = pixel_sample - ray_origin.clone();
-
-        Ray::from_origin_dir(&ray_origin, &ray_direction)
-    }
-
-    fn get_ray_motion_blur(&mut self, i: i32, j: i32) -> Ray {
-        let offset = self.sample_square();
-        let pixel_sample = self.pixel00_loc.clone()
-            + (i as f64 + offset.x()) * self.pixel_delta_u.clone()
-            + (j as f64 + offset.y()) * self.pixel_delta_v.clone();
-        let ray_origin = if self.defocus_angle < 0.0 {
-            self.center.clone()
+        let disk = if self.lens == Lens::Pinhole || self.defocus_angle <= 0.0 {
+            None
         } else {
-            self.defocus_disk_sample()
+            Some(Vec3::random_in_unit_disk(random))
+        };
+        let ray_time = self.sample_time(random);
+
+        let view = match &self.path {
+            Some(path) => Cow::Owned(self.compute_view(&path.keyframe(ray_time))),
+            None => Cow::Borrowed(&self.view),
+        };
+        let pixel_sample = view.pixel00_loc.clone()
+            + (i as f64 + offset.x()) * view.pixel_delta_u.clone()
+            + (j as f64 + offset.y()) * view.pixel_delta_v.clone();
+        let ray_origin = match disk {
+            Some(p) => {
+                view.center.clone()
+                    + (p[0] * view.defocus_disk_u.clone())
+                    + (p[1] * view.defocus_disk_v.clone())
+            }
+            None => view.center.clone(),
         };
 
         let ray_direction = pixel_sample - ray_origin.clone();
-        let ray_time = utl::random_f64();
 
         Ray::from_origin_dir_tm(&ray_origin, &ray_direction, ray_time)
     }
 
-    fn sample_square(&mut self) -> Vec3 {
-        Vec3::from_slice([
-            self.random.random_f64() - 0.5,
-            self.random.random_f64() - 0.5,
-            0.0,
-        ])
+    fn sample_time(&self, random: &mut utl::Random) -> f64 {
+        let s = self.shutter_curve.sample(random.random_f64());
+        self.shutter_open + s * (self.shutter_close - self.shutter_open)
     }
 
-    fn defocus_disk_sample(&self) -> Point3 {
-        let p = Vec3::random_in_unit_disk();
-
-        self.center.clone()
-            + (p[0] * self.defocus_disk_u.clone())
-            + (p[1] * self.defocus_disk_v.clone())
+    fn sample_square(random: &mut utl::Random) -> Vec3 {
+        Vec3::from_slice([random.random_f64() - 0.5, random.random_f64() - 0.5, 0.0])
     }
+}
 
-    pub fn ray_color<T: HittableV2>(ray: &Ray, world: &T) -> Color {
-        let mut rec = HitRecord::new();
-        if world.hit_v2(
-            &ray,
-            Interval::new_by_value(0.0, utl::constans::INFINITY),
-            &mut rec,
-        ) {
-            return 0.5 * (rec.normal.clone() + Color::from_slice([1.0, 1.0, 1.0]));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera_path::CameraKeyframe;
+    use crate::hittable_list::HittableList;
+    use crate::integrator::MaterialIntegrator;
+    use crate::material::{DielectricV3, Lambertian, Material};
+    use crate::sphere_material::SphereMat;
+
+    #[test]
+    fn test_render_tiles_same_as_sequential() {
+        let mut world = HittableList::new();
+        let material_ground: Option<Arc<dyn Material>> =
+            Some(Arc::new(Lambertian::new(&Color::from_slice([
+                0.8, 0.8, 0.0,
+            ]))));
+        let material_center: Option<Arc<dyn Material>> =
+            Some(Arc::new(Lambertian::new(&Color::from_slice([
+                0.1, 0.2, 0.5,
+            ]))));
+        let material_glass: Option<Arc<dyn Material>> = Some(Arc::new(DielectricV3::new(1.5)));
+        world.add(Arc::new(SphereMat::new(
+            &Point3::from_slice([0.0, -100.5, -1.0]),
+            100.0,
+            material_ground,
+        )));
+        world.add(Arc::new(SphereMat::new(
+            &Point3::from_slice([0.0, 0.0, -1.2]),
+            0.5,
+            material_center,
+        )));
+        world.add(Arc::new(SphereMat::new(
+            &Point3::from_slice([-1.0, 0.0, -1.0]),
+            0.5,
+            material_glass,
+        )));
+
+        let mut camera = Camera::new();
+        camera.aspect_ratio = 16.0 / 9.0;
+        camera.image_width = 32;
+        camera.samples_per_pixel = 4;
+        camera.max_depth = 10;
+        camera.defocus_angle = 0.6;
+        camera.focus_dist = 1.0;
+        camera.seed = 7;
+        camera.initialize();
+
+        let mut sequential = FrameBuffer::new(camera.image_width, camera.image_height);
+        for j in 0..camera.image_height {
+            for i in 0..camera.image_width {
+                let pixel_sum = camera.sample_pixel(i, j, &world, &MaterialIntegrator);
+                sequential.add_samples(i, j, &pixel_sum, 4);
+            }
         }
 
-        let unit_direction = Vec3::new_unit_vec(ray.direction().clone());
-        let a = 0.5 * (unit_direction.y() + 1.0);
+        for (n_threads, tile_size) in [(1, 16), (3, 5), (4, 7)] {
+            camera.n_threads = n_threads;
+            camera.tile_size = tile_size;
+            let framebuffer = camera.render_tiles(&world, &MaterialIntegrator);
 
-        (1.0 - a) * Color::from_slice([1.0, 1.0, 1.0]) + a * Color::from_slice([0.5, 0.7, 1.0])
-    }
-
-    pub fn ray_color_diffuse<T: HittableV2>(ray: &Ray, world: &T) -> Color {
-        let mut rec = HitRecord::new();
-        if world.hit_v2(
-            &ray,
-            Interval::new_by_value(0.0, utl::constans::INFINITY),
-            &mut rec,
-        ) {
-            let direction = Vec3::random_on_hemisphere(&rec.normal);
-            return 0.5 * Self::ray_color_diffuse(&Ray::from_origin_dir(&rec.p, &direction), world);
+            assert_eq!(framebuffer.width(), sequential.width());
+            assert_eq!(framebuffer.height(), sequential.height());
+            for (pixel, expected) in framebuffer.pixels().zip(sequential.pixels()) {
+                assert_eq!(pixel.e, expected.e);
+            }
+            assert_eq!(framebuffer.sample_count(3, 2), 4);
         }
-
-        let unit_direction = Vec3::new_unit_vec(ray.direction().clone());
-        let a = 0.5 * (unit_direction.y() + 1.0);
-
-        (1.0 - a) * Color::from_slice([1.0, 1.0, 1.0]) + a * Color::from_slice([0.5, 0.7, 1.0])
     }
 
-    pub fn ray_color_diffuse_max_depth<T: HittableV2>(ray: &Ray, depth: i32, world: &T) -> Color {
-        if depth <= 0 {
-            return Color::from_slice([0.0, 0.0, 0.0]);
+    #[test]
+    fn test_shutter() {
+        let mut camera = Camera::new();
+        camera.set_shutter_for_frame(12, 24.0, 180.0);
+        assert_eq!(camera.shutter_open, 0.5);
+        assert!((camera.shutter_close - (0.5 + 1.0 / 48.0)).abs() < 1e-15);
+
+        // times fall in the interval with the density of the curve
+        camera.shutter_curve = ShutterCurve::trapezoid(0.5, 0.25).unwrap();
+        let (n_samples, n_bins) = (100000, 8);
+        let mut histogram = vec![0.0; n_bins];
+        let mut random = utl::Random::new_for_sample(1, 0, 0, 0);
+        for _ in 0..n_samples {
+            let time = camera.sample_time(&mut random);
+            assert!(camera.shutter_open <= time && time < camera.shutter_close);
+            let s = (time - camera.shutter_open) / (camera.shutter_close - camera.shutter_open);
+            histogram[((s * n_bins as f64) as usize).min(n_bins - 1)] += 1.0;
         }
-
-        let mut rec = HitRecord::new();
-        if world.hit_v2(
-            &ray,
-            Interval::new_by_value(0.001, utl::constans::INFINITY),
-            &mut rec,
-        ) {
-            let direction = Vec3::random_on_hemisphere(&rec.normal);
-            return 0.5
-                * Self::ray_color_diffuse_max_depth(
-                    &Ray::from_origin_dir(&rec.p, &direction),
-                    depth - 1,
-                    world,
-                );
+        // the area under the curve is 1 - (0.5 + 0.25) / 2
+        let area = 0.625;
+        for (k, count) in histogram.into_iter().enumerate() {
+            let s = (k as f64 + 0.5) / n_bins as f64;
+            let expected = camera.shutter_curve.efficiency(s) / area / n_bins as f64;
+            assert!((count / n_samples as f64 - expected).abs() < 0.01);
         }
 
-        let unit_direction = Vec3::new_unit_vec(ray.direction().clone());
-        let a = 0.5 * (unit_direction.y() + 1.0);
-
-        (1.0 - a) * Color::from_slice([1.0, 1.0, 1.0]) + a * Color::from_slice([0.5, 0.7, 1.0])
-    }
-
-    pub fn ray_color_lambertian<T: HittableV2>(ray: &Ray, depth: i32, world: &T) -> Color {
-        if depth <= 0 {
-            return Color::from_slice([0.0, 0.0, 0.0]);
+        for (opening, closing) in [(0.6, 0.5), (-0.1, 0.5), (0.5, f64::NAN)] {
+            assert!(ShutterCurve::trapezoid(opening, closing).is_err());
         }
-
-        let mut rec = HitRecord::new();
-        if world.hit_v2(
-            &ray,
-            Interval::new_by_value(0.001, utl::constans::INFINITY),
-            &mut rec,
-        ) {
-            let direction = rec.normal.clone() + Vec3::random_unit_vector();
-            return 0.5
-                * Self::ray_color_diffuse_max_depth(
-                    &Ray::from_origin_dir(&rec.p, &direction),
-                    depth - 1,
-                    world,
-                );
+        // a trapezoid built directly is clamped to a valid one
+        let curve = ShutterCurve::Trapezoid {
+            opening: 0.75,
+            closing: 0.5,
+        };
+        assert!((curve.efficiency(0.9) - 0.4).abs() < 1e-12);
+        for xi in [0.0, 0.3, 0.7, 0.999] {
+            let s = curve.sample(xi);
+            assert!((0.0..=1.0).contains(&s), "{s}");
         }
-
-        let unit_direction = Vec3::new_unit_vec(ray.direction().clone());
-        let a = 0.5 * (unit_direction.y() + 1.0);
-
-        (1.0 - a) * Color::from_slice([1.0, 1.0, 1.0]) + a * Color::from_slice([0.5, 0.7, 1.0])
     }
 
-    pub fn ray_color_material<T: HittableMat>(ray: &Ray, depth: i32, world: &T) -> Color {
-        if depth <= 0 {
-            return Color::from_slice([0.0, 0.0, 0.0]);
-        }
+    #[test]
+    fn test_camera_path() {
+        let mut world = HittableList::new();
+        world.add(Arc::new(SphereMat::new(
+            &Point3::from_slice([0.0, 0.0, -2.0]),
+            0.5,
+            Some(Arc::new(Lambertian::new(&Color::from_slice([
+                0.1, 0.2, 0.5,
+            ])))),
+        )));
+        let render = |camera: &mut Camera| {
+            camera.image_width = 16;
+            camera.samples_per_pixel = 2;
+            camera.defocus_angle = 1.0;
+            camera.n_threads = 1;
+            camera.initialize();
+            camera.render_tiles(&world, &MaterialIntegrator)
+        };
 
-        let mut rec = HitRecordMat::new();
-        if world.hit_mat(
-            &ray,
-            Interval::new_by_value(0.001, utl::constans::INFINITY),
-            &mut rec,
-        ) {
-            let mut scatterd = Ray::new();
-            let mut attenuation = Color::new();
-            if rec.mat.clone().unwrap().as_ref().borrow().scatter(
-                &ray,
-                &rec,
-                &mut attenuation,
-                &mut scatterd,
-            ) {
-                return attenuation * Self::ray_color_material(&scatterd, depth - 1, world);
-            }
-            return Color::new();
+        let mut start = CameraKeyframe::new(0.0);
+        start.look_from = Point3::from_slice([-1.0, 0.0, 0.0]);
+        let mut end = CameraKeyframe::new(2.0);
+        end.look_from = Point3::from_slice([1.0, 0.5, 0.0]);
+        end.vfov = 40.0;
+        end.focus_dist = 2.0;
+        let path = CameraPath::new(vec![start, end]).unwrap();
+
+        // with the shutter open at one instant the path is a fixed camera
+        let mut moving = Camera::new();
+        moving.path = Some(path.clone());
+        (moving.shutter_open, moving.shutter_close) = (1.0, 1.0);
+        let middle = path.keyframe(1.0);
+        let mut fixed = Camera::new();
+        fixed.look_from = middle.look_from;
+        fixed.vfov = middle.vfov;
+        fixed.focus_dist = middle.focus_dist;
+        for (pixel, expected) in render(&mut moving)
+            .pixels()
+            .zip(render(&mut fixed).pixels())
+        {
+            assert_eq!(pixel.e, expected.e);
         }
 
-        let unit_direction = Vec3::new_unit_vec(ray.direction().clone());
-        let a = 0.5 * (unit_direction.y() + 1.0);
-
-        (1.0 - a) * Color::from_slice([1.0, 1.0, 1.0]) + a * Color::from_slice([0.5, 0.7, 1.0])
+        // one frame per keyframe interval
+        let mut frames = vec![];
+        moving
+            .render_sequence(&world, &MaterialIntegrator, 0..3, 1.0, 180.0, |frame, _| {
+                frames.push(frame);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(frames, [0, 1, 2]);
+        assert_eq!((moving.shutter_open, moving.shutter_close), (2.0, 2.5));
     }
 }
@@ -0,0 +1,112 @@
+use crate::animated_transform;
+use crate::vec3::{Point3, Vec3};
+use std::error::Error;
+
+/// camera placement at `time`
+#[derive(Debug, Clone)]
+pub struct CameraKeyframe {
+    pub time: f64,
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub v_up: Vec3,
+    pub vfov: f64,
+    pub focus_dist: f64,
+}
+
+impl CameraKeyframe {
+    /// the defaults of `Camera::new` at `time`
+    pub fn new(time: f64) -> Self {
+        Self {
+            time,
+            look_from: Point3::new(),
+            look_at: Point3::from_slice([0.0, 0.0, -1.0]),
+            v_up: Vec3::from_slice([0.0, 1.0, 0.0]),
+            vfov: 90.0,
+            focus_dist: 10.0,
+        }
+    }
+}
+
+/// camera placements interpolated between keyframes
+///
+/// every value is linear in time (the up vector is normalized). before the first and
+/// after the last keyframe the camera stays in place.
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    /// error if there are no keyframes or the times are not increasing
+    pub fn new(keyframes: Vec<CameraKeyframe>) -> Result<Self, Box<dyn Error>> {
+        animated_transform::check_times(
+            &keyframes
+                .iter()
+                .map(|keyframe| keyframe.time)
+                .collect::<Vec<_>>(),
+        )?;
+
+        Ok(Self { keyframes })
+    }
+
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        &self.keyframes
+    }
+
+    /// interpolated placement at `time`
+    pub fn keyframe(&self, time: f64) -> CameraKeyframe {
+        let k = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+        if k == 0 || k == self.keyframes.len() {
+            let mut keyframe = self.keyframes[k.saturating_sub(1)].clone();
+            keyframe.time = time;
+            return keyframe;
+        }
+
+        let (a, b) = (&self.keyframes[k - 1], &self.keyframes[k]);
+        let s = (time - a.time) / (b.time - a.time);
+        let lerp = |x: &Vec3, y: &Vec3| (1.0 - s) * x.clone() + s * y.clone();
+        CameraKeyframe {
+            time,
+            look_from: lerp(&a.look_from, &b.look_from),
+            look_at: lerp(&a.look_at, &b.look_at),
+            v_up: Vec3::new_unit_vec(lerp(
+                &Vec3::new_unit_vec(a.v_up.clone()),
+                &Vec3::new_unit_vec(b.v_up.clone()),
+            )),
+            vfov: (1.0 - s) * a.vfov + s * b.vfov,
+            focus_dist: (1.0 - s) * a.focus_dist + s * b.focus_dist,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camera_path() {
+        let mut start = CameraKeyframe::new(0.0);
+        start.look_from = Point3::from_slice([0.0, 1.0, 4.0]);
+        let mut end = CameraKeyframe::new(2.0);
+        end.look_from = Point3::from_slice([4.0, 1.0, 0.0]);
+        end.v_up = Vec3::from_slice([1.0, 0.0, 0.0]);
+        end.vfov = 30.0;
+        end.focus_dist = 4.0;
+        let path = CameraPath::new(vec![start, end]).unwrap();
+
+        let middle = path.keyframe(1.0);
+        assert_eq!(middle.look_from.e, [2.0, 1.0, 2.0]);
+        assert_eq!(middle.look_at.e, [0.0, 0.0, -1.0]);
+        let up = 0.5f64.sqrt();
+        assert!((middle.v_up.clone() - Vec3::from_slice([up, up, 0.0])).norm() < 1e-12);
+        assert_eq!((middle.vfov, middle.focus_dist), (60.0, 7.0));
+        // clamped outside the keyframes
+        assert_eq!(path.keyframe(3.0).look_from.e, [4.0, 1.0, 0.0]);
+        assert_eq!(path.keyframe(-1.0).vfov, 90.0);
+
+        assert!(CameraPath::new(vec![]).is_err());
+        assert!(CameraPath::new(vec![CameraKeyframe::new(1.0), CameraKeyframe::new(0.0)]).is_err());
+    }
+}
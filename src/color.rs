@@ -3,7 +3,7 @@ use crate::vec3::Vec3;
 pub type Color = Vec3;
 
 /// gamma correction by x^{\gamma}
-fn linear_to_gamma(linear_component: f64, gamma: f64) -> f64 {
+pub(crate) fn linear_to_gamma(linear_component: f64, gamma: f64) -> f64 {
     if linear_component > 0.0 {
         return linear_component.powf(gamma);
     }
@@ -11,8 +11,13 @@ fn linear_to_gamma(linear_component: f64, gamma: f64) -> f64 {
     0.0
 }
 
+/// Rec. 709 luminance of a linear color
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
 /// write color information (r,g,b) to *.ppm file
-pub fn write_color<T: std::io::Write>(
+pub fn write_color<T: std::io::Write + ?Sized>(
     out: &mut T,
     pixel_color: &Color,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -27,7 +32,7 @@ pub fn write_color<T: std::io::Write>(
     Ok(())
 }
 
-pub fn write_color_v2<T: std::io::Write>(
+pub fn write_color_v2<T: std::io::Write + ?Sized>(
     out: &mut T,
     pixel_color: &Color,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -44,7 +49,7 @@ pub fn write_color_v2<T: std::io::Write>(
 }
 
 /// write color information (r,g,b) to *.ppm file with gamma correction
-pub fn write_color_gamma<T: std::io::Write>(
+pub fn write_color_gamma<T: std::io::Write + ?Sized>(
     gamma: f64,
     out: &mut T,
     pixel_color: &Color,
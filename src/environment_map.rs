@@ -0,0 +1,278 @@
+use crate::background::Background;
+use crate::color::{luminance, Color};
+use crate::framebuffer::FrameBuffer;
+use crate::image_reader::load_image;
+use crate::ray::Ray;
+use crate::utl;
+use crate::vec3::Vec3;
+use std::error::Error;
+
+/// distant illumination from an equirectangular (latitude-longitude) image,
+/// importance sampled by luminance
+///
+/// the top row is straight up (+y), the center of the image looks towards -z.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    /// rotation around the y axis in degrees
+    pub rotation: f64,
+    /// scale of the radiance
+    pub intensity: f64,
+    /// sampling weight of each pixel: luminance times the solid angle of its row
+    weights: Vec<f64>,
+    total_weight: f64,
+    /// cumulative distribution of the rows (height + 1 values in [0, 1])
+    marginal_cdf: Vec<f64>,
+    /// cumulative distribution of the pixels within each row (width + 1 values per row)
+    conditional_cdf: Vec<f64>,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: &FrameBuffer) -> Self {
+        let (width, height) = (
+            image.width().max(1) as usize,
+            image.height().max(1) as usize,
+        );
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut weights = Vec::with_capacity(width * height);
+        for j in 0..height {
+            let sin_theta = (utl::constans::PI * (j as f64 + 0.5) / height as f64).sin();
+            for i in 0..width {
+                let color = if image.width() > 0 && image.height() > 0 {
+                    image.pixel(i as i32, j as i32)
+                } else {
+                    Color::new()
+                };
+                weights.push(luminance(&color).max(0.0) * sin_theta);
+                pixels.push(color);
+            }
+        }
+
+        let mut conditional_cdf = Vec::with_capacity((width + 1) * height);
+        let mut row_weights = Vec::with_capacity(height);
+        for row in weights.chunks_exact(width) {
+            let row_weight = row.iter().sum::<f64>();
+            let mut sum = 0.0;
+            conditional_cdf.push(0.0);
+            for (i, weight) in row.iter().enumerate() {
+                sum += weight;
+                // rows without weight are never chosen, keep their distribution valid anyway
+                conditional_cdf.push(if row_weight > 0.0 {
+                    sum / row_weight
+                } else {
+                    (i + 1) as f64 / width as f64
+                });
+            }
+            row_weights.push(row_weight);
+        }
+        let total_weight = row_weights.iter().sum::<f64>();
+        let mut marginal_cdf = Vec::with_capacity(height + 1);
+        let mut sum = 0.0;
+        marginal_cdf.push(0.0);
+        for row_weight in row_weights {
+            sum += row_weight;
+            marginal_cdf.push(if total_weight > 0.0 {
+                sum / total_weight
+            } else {
+                0.0
+            });
+        }
+
+        Self {
+            width,
+            height,
+            pixels,
+            rotation: 0.0,
+            intensity: 1.0,
+            weights,
+            total_weight,
+            marginal_cdf,
+            conditional_cdf,
+        }
+    }
+
+    /// load an *.hdr or *.pfm image
+    pub fn load(file_name: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(&load_image(file_name)?))
+    }
+
+    /// rotate `direction` around the y axis by `degrees`
+    fn rotate(direction: &Vec3, degrees: f64) -> Vec3 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Vec3::from_slice([
+            cos * direction.x() + sin * direction.z(),
+            direction.y(),
+            -sin * direction.x() + cos * direction.z(),
+        ])
+    }
+
+    /// image coordinates in [0, 1] of a world direction
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let d = Vec3::new_unit_vec(Self::rotate(direction, -self.rotation));
+        let u = 0.5 + d.x().atan2(-d.z()) / (2.0 * utl::constans::PI);
+        let v = d.y().clamp(-1.0, 1.0).acos() / utl::constans::PI;
+
+        (u, v)
+    }
+
+    /// world direction of image coordinates in [0, 1]
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let phi = 2.0 * utl::constans::PI * (u - 0.5);
+        let theta = utl::constans::PI * v;
+        let d = Vec3::from_slice([
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        ]);
+
+        Self::rotate(&d, self.rotation)
+    }
+
+    fn pixel_index(&self, u: f64, v: f64) -> usize {
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        j * self.width + i
+    }
+
+    /// density per solid angle of a direction in pixel `index`, theta is its polar angle
+    fn pdf_by_index(&self, index: usize, sin_theta: f64) -> f64 {
+        if self.total_weight <= 0.0 || sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let pdf_uv = self.weights[index] / self.total_weight * (self.width * self.height) as f64;
+
+        pdf_uv / (2.0 * utl::constans::PI * utl::constans::PI * sin_theta)
+    }
+}
+
+/// index k of the interval cdf[k]..cdf[k + 1] containing `x` and the position within it
+fn sample_cdf(cdf: &[f64], x: f64) -> (usize, f64) {
+    let k = cdf
+        .partition_point(|&c| c <= x)
+        .saturating_sub(1)
+        .min(cdf.len() - 2);
+    let size = cdf[k + 1] - cdf[k];
+    let offset = if size > 0.0 {
+        ((x - cdf[k]) / size).clamp(0.0, 1.0)
+    } else {
+        0.5
+    };
+
+    (k, offset)
+}
+
+impl Background for EnvironmentMap {
+    fn color(&self, ray: &Ray) -> Color {
+        let (u, v) = self.direction_to_uv(ray.direction());
+
+        self.intensity * self.pixels[self.pixel_index(u, v)].clone()
+    }
+
+    fn sample(&self, random: &mut utl::Random) -> Option<(Vec3, f64)> {
+        if self.total_weight <= 0.0 {
+            return None;
+        }
+        let (j, dv) = sample_cdf(&self.marginal_cdf, random.random_f64());
+        let row = &self.conditional_cdf[j * (self.width + 1)..(j + 1) * (self.width + 1)];
+        let (i, du) = sample_cdf(row, random.random_f64());
+
+        let (u, v) = (
+            (i as f64 + du) / self.width as f64,
+            (j as f64 + dv) / self.height as f64,
+        );
+        let pdf = self.pdf_by_index(j * self.width + i, (utl::constans::PI * v).sin());
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some((self.uv_to_direction(u, v), pdf))
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+
+        self.pdf_by_index(self.pixel_index(u, v), (utl::constans::PI * v).sin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Point3;
+
+    fn test_image() -> FrameBuffer {
+        let mut image = FrameBuffer::new(16, 8);
+        for j in 0..8 {
+            for i in 0..16 {
+                image.set_pixel(i, j, &Color::from_slice([0.1, 0.2, 0.3 + 0.01 * i as f64]));
+            }
+        }
+        // a small bright "sun"
+        image.set_pixel(5, 2, &Color::from_slice([500.0, 400.0, 300.0]));
+        image
+    }
+
+    #[test]
+    fn test_mapping() {
+        let mut map = EnvironmentMap::new(&test_image());
+        for rotation in [0.0, 30.0, -135.0] {
+            map.rotation = rotation;
+            for (u, v) in [(0.3, 0.2), (0.5, 0.5), (0.9, 0.75)] {
+                let direction = map.uv_to_direction(u, v);
+                let (u2, v2) = map.direction_to_uv(&direction);
+                assert!((u - u2).abs() < 1e-9 && (v - v2).abs() < 1e-9, "{u} {v}");
+            }
+        }
+
+        map.rotation = 0.0;
+        let forward = Ray::from_origin_dir(&Point3::new(), &Vec3::from_slice([0.0, 0.0, -1.0]));
+        assert_eq!(map.color(&forward).e, [0.1, 0.2, 0.3 + 0.01 * 8.0]);
+        // rotating the map by 90 degrees moves -x to the center of the image
+        map.rotation = 90.0;
+        let (u, _) = map.direction_to_uv(&Vec3::from_slice([-1.0, 0.0, 0.0]));
+        assert!((u - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sample_pdf() {
+        let mut map = EnvironmentMap::new(&test_image());
+        map.rotation = 40.0;
+        let mut random = utl::Random::new_by_seed(7);
+
+        let n = 20000;
+        let mut sun = 0;
+        for _ in 0..n {
+            let (direction, pdf) = map.sample(&mut random).unwrap();
+            assert!((pdf - map.pdf(&direction)).abs() < 1e-6 * pdf, "{pdf}");
+            let (u, v) = map.direction_to_uv(&direction);
+            if map.pixel_index(u, v) == 2 * 16 + 5 {
+                sun += 1;
+            }
+        }
+        // the sun has most of the energy
+        assert!(sun as f64 > 0.5 * n as f64, "{sun}");
+
+        // the pdf integrates to 1 over the sphere (d omega = 2 pi^2 sin(theta) du dv)
+        let (nu, nv) = (256, 128);
+        let mut integral = 0.0;
+        for j in 0..nv {
+            for i in 0..nu {
+                let (u, v) = ((i as f64 + 0.5) / nu as f64, (j as f64 + 0.5) / nv as f64);
+                let sin_theta = (utl::constans::PI * v).sin();
+                integral += map.pdf(&map.uv_to_direction(u, v))
+                    * 2.0
+                    * utl::constans::PI
+                    * utl::constans::PI
+                    * sin_theta
+                    / (nu * nv) as f64;
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-3, "{integral}");
+
+        assert!(EnvironmentMap::new(&FrameBuffer::new(4, 2))
+            .sample(&mut random)
+            .is_none());
+    }
+}
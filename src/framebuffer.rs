@@ -0,0 +1,110 @@
+use crate::color::Color;
+
+/// HDR image in linear RGB
+///
+/// each pixel keeps the sum of its samples and the number of samples,
+/// so more samples can be accumulated later.
+#[derive(Debug, Clone)]
+pub struct FrameBuffer {
+    width: i32,
+    height: i32,
+    sums: Vec<Color>,
+    sample_counts: Vec<u32>,
+}
+
+impl FrameBuffer {
+    /// black image of width x height without samples
+    ///
+    /// panics if the number of pixels does not fit in memory addresses
+    /// (readers check the size before).
+    pub fn new(width: i32, height: i32) -> Self {
+        let n = (width.max(0) as usize)
+            .checked_mul(height.max(0) as usize)
+            .expect("image too large");
+        Self {
+            width,
+            height,
+            sums: vec![Color::new(); n],
+            sample_counts: vec![0; n],
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn index(&self, i: i32, j: i32) -> usize {
+        assert!(
+            0 <= i && i < self.width && 0 <= j && j < self.height,
+            "pixel ({i}, {j}) is out of {}x{} image",
+            self.width,
+            self.height
+        );
+        j as usize * self.width as usize + i as usize
+    }
+
+    /// add one sample to pixel (i, j)
+    pub fn add_sample(&mut self, i: i32, j: i32, color: &Color) {
+        self.add_samples(i, j, color, 1);
+    }
+
+    /// add `count` samples whose sum is `sum` to pixel (i, j)
+    pub fn add_samples(&mut self, i: i32, j: i32, sum: &Color, count: u32) {
+        let index = self.index(i, j);
+        self.sums[index] += sum.clone();
+        self.sample_counts[index] += count;
+    }
+
+    /// overwrite pixel (i, j) by `color` as a single sample
+    pub fn set_pixel(&mut self, i: i32, j: i32, color: &Color) {
+        let index = self.index(i, j);
+        self.sums[index] = color.clone();
+        self.sample_counts[index] = 1;
+    }
+
+    /// mean of the samples of pixel (i, j) (black if there is no sample)
+    pub fn pixel(&self, i: i32, j: i32) -> Color {
+        let index = self.index(i, j);
+        match self.sample_counts[index] {
+            0 => Color::new(),
+            count => self.sums[index].clone() / count as f64,
+        }
+    }
+
+    pub fn sample_count(&self, i: i32, j: i32) -> u32 {
+        self.sample_counts[self.index(i, j)]
+    }
+
+    /// mean colors of all pixels in scanline order (top to bottom, left to right)
+    pub fn pixels(&self) -> impl Iterator<Item = Color> + '_ {
+        (0..self.height).flat_map(move |j| (0..self.width).map(move |i| self.pixel(i, j)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_mean() {
+        let mut framebuffer = FrameBuffer::new(3, 2);
+        framebuffer.add_sample(1, 1, &Color::from_slice([1.0, 2.0, 3.0]));
+        framebuffer.add_sample(1, 1, &Color::from_slice([3.0, 4.0, 5.0]));
+        framebuffer.add_samples(2, 0, &Color::from_slice([4.0, 4.0, 4.0]), 4);
+
+        assert_eq!(framebuffer.pixel(1, 1).e, [2.0, 3.0, 4.0]);
+        assert_eq!(framebuffer.sample_count(1, 1), 2);
+        assert_eq!(framebuffer.pixel(2, 0).e, [1.0, 1.0, 1.0]);
+        assert_eq!(framebuffer.pixel(0, 0).e, [0.0, 0.0, 0.0]);
+        assert_eq!(framebuffer.sample_count(0, 0), 0);
+
+        let pixels = framebuffer.pixels().collect::<Vec<_>>();
+        assert_eq!(pixels.len(), 6);
+        assert_eq!(pixels[2].e, [1.0, 1.0, 1.0]);
+        assert_eq!(pixels[4].e, [2.0, 3.0, 4.0]);
+    }
+}
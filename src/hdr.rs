@@ -0,0 +1,390 @@
+use crate::color::Color;
+use crate::framebuffer::FrameBuffer;
+use crate::image_reader::ImageReader;
+use crate::image_writer::ImageWriter;
+use std::error::Error;
+use std::io::{Read, Write};
+
+/// shortest run of equal bytes worth encoding as a run
+const MIN_RUN: usize = 4;
+
+/// write Radiance *.hdr image (RGBE), radiance above 1.0 is kept
+pub struct HdrWriter {
+    /// run length encode the scanlines (only possible for widths in 8..=32767)
+    pub rle: bool,
+}
+
+impl Default for HdrWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HdrWriter {
+    pub fn new() -> Self {
+        Self { rle: true }
+    }
+}
+
+impl ImageWriter for HdrWriter {
+    fn write(&self, framebuffer: &FrameBuffer, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let (width, height) = (framebuffer.width(), framebuffer.height());
+        std::write!(
+            out,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n"
+        )?;
+
+        let rle = self.rle && (8..=32767).contains(&width);
+        let mut scanline = Vec::with_capacity(width.max(0) as usize);
+        for j in 0..height {
+            scanline.clear();
+            scanline.extend((0..width).map(|i| to_rgbe(&framebuffer.pixel(i, j))));
+
+            if rle {
+                out.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+                let mut encoded = vec![];
+                let mut channel = Vec::with_capacity(scanline.len());
+                for c in 0..4 {
+                    channel.clear();
+                    channel.extend(scanline.iter().map(|rgbe| rgbe[c]));
+                    rle_encode(&channel, &mut encoded);
+                }
+                out.write_all(&encoded)?;
+            } else {
+                out.write_all(&scanline.concat())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// read Radiance *.hdr image (RGBE, flat or run length encoded scanlines)
+pub struct HdrReader;
+
+impl ImageReader for HdrReader {
+    fn read(&self, input: &mut dyn Read) -> Result<FrameBuffer, Box<dyn Error>> {
+        let mut data = vec![];
+        input.read_to_end(&mut data)?;
+
+        // header lines end with an empty line, then the resolution line follows
+        let mut lines = data.split(|&c| c == b'\n');
+        let mut pos = 0;
+        let mut next_line = || {
+            let line = lines.next()?;
+            pos += line.len() + 1;
+            Some(String::from_utf8_lossy(line).into_owned())
+        };
+        match next_line() {
+            Some(line) if line.starts_with("#?") => {}
+            _ => return Err("not a Radiance HDR image (missing #? signature)".into()),
+        }
+        loop {
+            match next_line() {
+                None => return Err("unexpected end of data in header".into()),
+                Some(line) if line.trim().is_empty() => break,
+                Some(line) => {
+                    if let Some(format) = line.strip_prefix("FORMAT=") {
+                        if format.trim() != "32-bit_rle_rgbe" {
+                            return Err(format!("unsupported format {format:?}").into());
+                        }
+                    }
+                }
+            }
+        }
+        let resolution = next_line().ok_or("unexpected end of data in resolution")?;
+        let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (width.parse::<i32>(), height.parse::<i32>()),
+            _ => return Err(format!("unsupported resolution line {resolution:?}").into()),
+        };
+        let (width, height) = match (width, height) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => (width, height),
+            _ => return Err(format!("invalid resolution line {resolution:?}").into()),
+        };
+
+        let mut data = data.get(pos..).unwrap_or_default();
+        // reject sizes the data cannot hold before allocating: a run length encoded
+        // scanline takes at least 2 bytes per channel per 127 pixels, a flat one 4 per pixel
+        let min_scanline_bytes = if (8..=32767).contains(&width) {
+            4 + 8 * (width as usize).div_ceil(127)
+        } else {
+            4 * width as usize
+        };
+        let min_bytes = min_scanline_bytes
+            .checked_mul(height as usize)
+            .ok_or("image too large")?;
+        if data.len() < min_bytes {
+            return Err(format!(
+                "unexpected end of data: {} bytes of scanlines, expected at least {min_bytes}",
+                data.len()
+            )
+            .into());
+        }
+        let mut framebuffer = FrameBuffer::new(width, height);
+        let mut scanline = vec![[0u8; 4]; width as usize];
+        for j in 0..height {
+            data = read_scanline(data, &mut scanline).map_err(|e| format!("scanline {j}: {e}"))?;
+            for (i, rgbe) in scanline.iter().enumerate() {
+                framebuffer.set_pixel(i as i32, j, &from_rgbe(*rgbe));
+            }
+        }
+
+        Ok(framebuffer)
+    }
+}
+
+/// decode one scanline from `data` and return the rest
+fn read_scanline<'a>(data: &'a [u8], scanline: &mut [[u8; 4]]) -> Result<&'a [u8], String> {
+    let width = scanline.len();
+    let is_rle = (8..=32767).contains(&width)
+        && data.len() >= 4
+        && data[0] == 2
+        && data[1] == 2
+        && data[2] & 0x80 == 0;
+    if !is_rle {
+        if data.len() < 4 * width {
+            return Err("unexpected end of data".into());
+        }
+        for (rgbe, bytes) in scanline.iter_mut().zip(data.chunks_exact(4)) {
+            rgbe.copy_from_slice(bytes);
+        }
+        return Ok(&data[4 * width..]);
+    }
+
+    if ((data[2] as usize) << 8 | data[3] as usize) != width {
+        return Err("run length encoded width does not match the image width".into());
+    }
+    let mut pos = 4;
+    for c in 0..4 {
+        let mut i = 0;
+        while i < width {
+            let count = *data.get(pos).ok_or("unexpected end of data")? as usize;
+            if count > 128 {
+                let count = count - 128;
+                let value = *data.get(pos + 1).ok_or("unexpected end of data")?;
+                if i + count > width {
+                    return Err("run exceeds the scanline".into());
+                }
+                scanline[i..i + count]
+                    .iter_mut()
+                    .for_each(|rgbe| rgbe[c] = value);
+                (i, pos) = (i + count, pos + 2);
+            } else {
+                if count == 0 || i + count > width {
+                    return Err("invalid literal count".into());
+                }
+                let values = data
+                    .get(pos + 1..pos + 1 + count)
+                    .ok_or("unexpected end of data")?;
+                for (rgbe, value) in scanline[i..i + count].iter_mut().zip(values) {
+                    rgbe[c] = *value;
+                }
+                (i, pos) = (i + count, pos + 1 + count);
+            }
+        }
+    }
+
+    Ok(&data[pos..])
+}
+
+/// shared exponent encoding: mantissas in [0, 256), exponent biased by 128
+pub fn to_rgbe(color: &Color) -> [u8; 4] {
+    let (r, g, b) = (color.x().max(0.0), color.y().max(0.0), color.z().max(0.0));
+    let v = r.max(g).max(b);
+    if v < 1e-32 || !v.is_finite() {
+        return [0, 0, 0, 0];
+    }
+
+    // v = m * 2^e with m in [0.5, 1)
+    let mut e = v.log2().floor() as i32 + 1;
+    if v / 2f64.powi(e) >= 1.0 {
+        e += 1;
+    }
+    let scale = 256.0 / 2f64.powi(e);
+
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (e + 128).clamp(0, 255) as u8,
+    ]
+}
+
+/// decode RGBE to the center of its quantization interval
+pub fn from_rgbe(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new();
+    }
+    let scale = 2f64.powi(rgbe[3] as i32 - (128 + 8));
+
+    Color::from_slice([
+        (rgbe[0] as f64 + 0.5) * scale,
+        (rgbe[1] as f64 + 0.5) * scale,
+        (rgbe[2] as f64 + 0.5) * scale,
+    ])
+}
+
+/// adaptive run length encoding of one channel of a scanline:
+/// (128 + n, value) for a run of n equal bytes, (n, n bytes) for literals
+fn rle_encode(data: &[u8], out: &mut Vec<u8>) {
+    let mut cur = 0;
+    while cur < data.len() {
+        // find the start of the next run of at least MIN_RUN bytes
+        let (mut run_start, mut run_count, mut old_run_count) = (cur, 0, 0);
+        while run_count < MIN_RUN && run_start < data.len() {
+            run_start += run_count;
+            old_run_count = run_count;
+            run_count = 1;
+            while run_start + run_count < data.len()
+                && run_count < 127
+                && data[run_start] == data[run_start + run_count]
+            {
+                run_count += 1;
+            }
+        }
+
+        // a short run just before the long one is still cheaper as a run
+        if old_run_count > 1 && old_run_count == run_start - cur {
+            out.extend_from_slice(&[128 + old_run_count as u8, data[cur]]);
+            cur = run_start;
+        }
+
+        while cur < run_start {
+            let n = (run_start - cur).min(128);
+            out.push(n as u8);
+            out.extend_from_slice(&data[cur..cur + n]);
+            cur += n;
+        }
+
+        if run_count >= MIN_RUN {
+            out.extend_from_slice(&[128 + run_count as u8, data[run_start]]);
+            cur += run_count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rle_decode(data: &[u8], n: usize) -> (Vec<u8>, usize) {
+        let (mut out, mut pos) = (vec![], 0);
+        while out.len() < n {
+            let count = data[pos] as usize;
+            if count > 128 {
+                out.extend(std::iter::repeat_n(data[pos + 1], count - 128));
+                pos += 2;
+            } else {
+                out.extend_from_slice(&data[pos + 1..pos + 1 + count]);
+                pos += 1 + count;
+            }
+        }
+        assert_eq!(out.len(), n);
+        (out, pos)
+    }
+
+    #[test]
+    fn test_rgbe() {
+        for c in [0.001, 0.3, 0.5, 1.0, 1.5, 255.0, 1000.0, 1e6] {
+            let color = Color::from_slice([c, 0.5 * c, 0.0]);
+            let decoded = from_rgbe(to_rgbe(&color));
+            assert!((decoded.x() - c).abs() <= c / 128.0, "{c}");
+            assert!((decoded.y() - 0.5 * c).abs() <= c / 128.0, "{c}");
+            assert!(decoded.z() < c / 128.0, "{c}");
+        }
+        assert_eq!(
+            to_rgbe(&Color::from_slice([1.0, 0.5, 0.0])),
+            [128, 64, 0, 129]
+        );
+        assert_eq!(to_rgbe(&Color::new()), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_rle_round_trip() {
+        let mut data = vec![1, 2, 3, 3, 3, 4, 5, 5, 5, 5, 5, 6];
+        data.extend(vec![7; 300]);
+        data.extend((0..300).map(|k| (k % 256) as u8));
+
+        let mut encoded = vec![];
+        rle_encode(&data, &mut encoded);
+        assert!(encoded.len() < data.len());
+        assert_eq!(rle_decode(&encoded, data.len()), (data, encoded.len()));
+    }
+
+    #[test]
+    fn test_hdr_writer() {
+        let mut framebuffer = FrameBuffer::new(10, 2);
+        for j in 0..2 {
+            for i in 0..10 {
+                let c = if i < 5 { 4.0 } else { i as f64 * 0.1 };
+                framebuffer.set_pixel(i, j, &Color::from_slice([c, c, j as f64]));
+            }
+        }
+
+        for rle in [true, false] {
+            let mut writer = HdrWriter::new();
+            writer.rle = rle;
+            let mut out = vec![];
+            writer.write(&framebuffer, &mut out).unwrap();
+
+            let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 10\n";
+            assert_eq!(&out[..header.len()], header);
+            let mut data = &out[header.len()..];
+            for j in 0..2 {
+                let scanline = if rle {
+                    assert_eq!(data[..4], [2, 2, 0, 10]);
+                    data = &data[4..];
+                    let mut channels = vec![];
+                    for _ in 0..4 {
+                        let (channel, n) = rle_decode(data, 10);
+                        channels.push(channel);
+                        data = &data[n..];
+                    }
+                    (0..10)
+                        .map(|i| [0, 1, 2, 3].map(|c| channels[c][i]))
+                        .collect::<Vec<_>>()
+                } else {
+                    let scanline = data[..40]
+                        .chunks(4)
+                        .map(|rgbe| [rgbe[0], rgbe[1], rgbe[2], rgbe[3]])
+                        .collect::<Vec<_>>();
+                    data = &data[40..];
+                    scanline
+                };
+                for (i, rgbe) in scanline.into_iter().enumerate() {
+                    assert_eq!(rgbe, to_rgbe(&framebuffer.pixel(i as i32, j)));
+                }
+            }
+            assert!(data.is_empty());
+
+            let decoded = HdrReader.read(&mut &out[..]).unwrap();
+            for (pixel, expected) in decoded.pixels().zip(framebuffer.pixels()) {
+                assert_eq!(pixel.e, from_rgbe(to_rgbe(&expected)).e);
+            }
+        }
+
+        for (data, message) in [
+            (&b"P6\n"[..], "not a Radiance HDR image"),
+            (
+                b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n",
+                "unsupported format",
+            ),
+            (b"#?RADIANCE\n\n+X 1 -Y 1\n", "unsupported resolution line"),
+            (
+                b"#?RADIANCE\n\n-Y 1 +X 2\n\x80\x80\x80\x81",
+                "unexpected end of data: 4 bytes",
+            ),
+            (
+                b"#?RADIANCE\n\n-Y 100000 +X 100000\n\x02\x02\x86\xa0",
+                "unexpected end of data: 4 bytes",
+            ),
+            (
+                b"#?RADIANCE\n\n-Y 1 +X 8\n\x02\x02\x00\x08\x88\x01\x88\x01\x88\x01\x82\x01",
+                "scanline 0: unexpected end",
+            ),
+        ] {
+            let error = HdrReader.read(&mut &data[..]).unwrap_err();
+            assert!(error.to_string().starts_with(message), "{error}");
+        }
+    }
+}
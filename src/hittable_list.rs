@@ -2,11 +2,16 @@ use crate::hittable::{HitRecord, Hittable, HittableV2};
 use crate::hittable_material::{HitRecordMat, HittableMat};
 use crate::interval::Interval;
 use crate::ray::Ray;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct HittableList<T> {
-    pub objects: Vec<Rc<RefCell<T>>>,
+    pub objects: Vec<Arc<T>>,
+}
+
+impl<T> Default for HittableList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> HittableList<T> {
@@ -14,7 +19,7 @@ impl<T> HittableList<T> {
         Self { objects: vec![] }
     }
 
-    pub fn from_object(object: Rc<RefCell<T>>) -> Self {
+    pub fn from_object(object: Arc<T>) -> Self {
         Self {
             objects: vec![object],
         }
@@ -24,7 +29,7 @@ impl<T> HittableList<T> {
         self.objects.clear();
     }
 
-    pub fn add(&mut self, object: Rc<RefCell<T>>) {
+    pub fn add(&mut self, object: Arc<T>) {
         self.objects.push(object);
     }
 }
@@ -36,11 +41,7 @@ impl<T: Hittable> Hittable for HittableList<T> {
         let mut closet_so_far = ray_tmax;
 
         for object in &self.objects {
-            if object
-                .as_ref()
-                .borrow()
-                .hit(&ray, ray_tmin, closet_so_far, &mut tmp_rec)
-            {
+            if object.hit(ray, ray_tmin, closet_so_far, &mut tmp_rec) {
                 hit_anything = true;
                 closet_so_far = tmp_rec.t;
                 *rec = tmp_rec.clone();
@@ -58,8 +59,8 @@ impl<T: HittableV2> HittableV2 for HittableList<T> {
         let mut closet_so_far = ray_t.max;
 
         for object in &self.objects {
-            if object.as_ref().borrow().hit_v2(
-                &ray,
+            if object.hit_v2(
+                ray,
                 Interval::new_by_value(ray_t.min, closet_so_far),
                 &mut tmp_rec,
             ) {
@@ -79,8 +80,8 @@ impl<T: HittableMat> HittableMat for HittableList<T> {
         let mut closet_so_far = ray_t.max;
 
         for object in &self.objects {
-            if object.as_ref().borrow().hit_mat(
-                &ray,
+            if object.hit_mat(
+                ray,
                 Interval::new_by_value(ray_t.min, closet_so_far),
                 &mut tmp_rec,
             ) {
@@ -2,15 +2,20 @@ use crate::aabb::AaBb;
 use crate::hittable_material::{HitRecordMat, HittableAaBb};
 use crate::interval::Interval;
 use crate::ray::Ray;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::Arc;
 
-pub struct HittableListAaBb<T> {
-    pub objects: Vec<Rc<RefCell<T>>>,
+pub struct HittableListAaBb<T: ?Sized> {
+    pub objects: Vec<Arc<T>>,
     bbox: AaBb,
 }
 
-impl<T: HittableAaBb> HittableListAaBb<T> {
+impl<T: HittableAaBb + ?Sized> Default for HittableListAaBb<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: HittableAaBb + ?Sized> HittableListAaBb<T> {
     pub fn new() -> Self {
         Self {
             objects: vec![],
@@ -18,32 +23,34 @@ impl<T: HittableAaBb> HittableListAaBb<T> {
         }
     }
 
-    pub fn from_object(object: Rc<RefCell<T>>) -> Self {
+    pub fn from_object(object: Arc<T>) -> Self {
+        let bbox = object.bounding_box();
         Self {
             objects: vec![object],
-            bbox: AaBb::new(),
+            bbox,
         }
     }
 
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.bbox = AaBb::new();
     }
 
-    pub fn add(&mut self, object: Rc<RefCell<T>>) {
+    pub fn add(&mut self, object: Arc<T>) {
         self.objects.push(object.clone());
-        self.bbox = AaBb::new_by_two_aabb(&self.bbox, &object.as_ref().borrow().bounding_box());
+        self.bbox = AaBb::new_by_two_aabb(&self.bbox, &object.bounding_box());
     }
 }
 
-impl<T: HittableAaBb> HittableAaBb for HittableListAaBb<T> {
+impl<T: HittableAaBb + ?Sized> HittableAaBb for HittableListAaBb<T> {
     fn hit_aabb(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
         let mut tmp_rec = HitRecordMat::new();
         let mut hit_anything = false;
         let mut closet_so_far = ray_t.max;
 
         for object in &self.objects {
-            if object.as_ref().borrow().hit_aabb(
-                &ray,
+            if object.hit_aabb(
+                ray,
                 Interval::new_by_value(ray_t.min, closet_so_far),
                 &mut tmp_rec,
             ) {
@@ -1,20 +1,34 @@
 use crate::aabb::AaBb;
+use crate::color::Color;
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::utl;
 use crate::vec3::{Point3, Vec3};
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct HitRecordMat {
     pub p: Point3,
     pub normal: Vec3,
-    pub mat: Option<Rc<RefCell<dyn Material>>>,
+    pub mat: Option<Arc<dyn Material>>,
     pub t: f64,
+    /// surface coordinates of the hit point
+    pub u: f64,
+    pub v: f64,
+    /// barycentric coordinates of the hit point on a triangle
+    pub barycentric: [f64; 3],
+    /// interpolated vertex color of a mesh with colors (None otherwise)
+    pub color: Option<Color>,
     pub front_face: bool,
 }
 
+impl Default for HitRecordMat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HitRecordMat {
     pub fn new() -> Self {
         Self {
@@ -22,6 +36,10 @@ impl HitRecordMat {
             normal: Vec3::new(),
             mat: None,
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            barycentric: [0.0; 3],
+            color: None,
             front_face: true,
         }
     }
@@ -34,13 +52,22 @@ impl HitRecordMat {
             -outward_normal.clone()
         };
     }
+
+    /// u, v of a sphere by the angles of the unit `outward_normal`:
+    /// u from the longitude around y (0 at -x), v from the latitude (0 at -y)
+    pub fn set_sphere_uv(&mut self, outward_normal: &Vec3) {
+        let theta = (-outward_normal.y()).clamp(-1.0, 1.0).acos();
+        let phi = (-outward_normal.z()).atan2(outward_normal.x()) + utl::constans::PI;
+        self.u = phi / (2.0 * utl::constans::PI);
+        self.v = theta / utl::constans::PI;
+    }
 }
 
 pub trait HittableMat {
     fn hit_mat(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool;
 }
 
-pub trait HittableAaBb {
+pub trait HittableAaBb: Send + Sync {
     fn hit_aabb(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool;
     fn bounding_box(&self) -> AaBb;
 }
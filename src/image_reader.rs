@@ -0,0 +1,314 @@
+use crate::color::Color;
+use crate::framebuffer::FrameBuffer;
+use crate::hdr::HdrReader;
+use crate::pfm::PfmReader;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// parses an image format into a framebuffer
+pub trait ImageReader {
+    fn read(&self, input: &mut dyn Read) -> Result<FrameBuffer, Box<dyn Error>>;
+
+    /// read the file `file_name`
+    fn load(&self, file_name: &str) -> Result<FrameBuffer, Box<dyn Error>> {
+        let mut input = BufReader::new(File::open(file_name)?);
+        self.read(&mut input)
+            .map_err(|e| format!("{file_name}: {e}").into())
+    }
+}
+
+/// reader chosen by the extension of `file_name`: *.ppm, *.pgm, *.hdr or *.pfm
+pub fn reader_for_file(file_name: &str) -> Result<Box<dyn ImageReader>, Box<dyn Error>> {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("ppm") | Some("pgm") => Ok(Box::new(PpmReader::new())),
+        Some("hdr") => Ok(Box::new(HdrReader)),
+        Some("pfm") => Ok(Box::new(PfmReader)),
+        _ => {
+            Err(format!("{file_name}: unknown image format (use .ppm, .pgm, .hdr or .pfm)").into())
+        }
+    }
+}
+
+/// read `file_name` in the format given by its extension
+pub fn load_image(file_name: &str) -> Result<FrameBuffer, Box<dyn Error>> {
+    reader_for_file(file_name)?.load(file_name)
+}
+
+/// read *.ppm (P3, P6) and *.pgm (P2, P5) images, 8-bit or 16-bit
+pub struct PpmReader {
+    /// gamma the image was written with (None: samples are linear),
+    /// the samples are converted back to linear by x^{1/\gamma}
+    pub gamma: Option<f64>,
+}
+
+impl Default for PpmReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PpmReader {
+    /// read samples as they are
+    pub fn new() -> Self {
+        Self { gamma: None }
+    }
+
+    /// undo the gamma correction of `PpmWriter::new_gamma(gamma)`
+    pub fn new_gamma(gamma: f64) -> Self {
+        Self { gamma: Some(gamma) }
+    }
+}
+
+impl ImageReader for PpmReader {
+    fn read(&self, input: &mut dyn Read) -> Result<FrameBuffer, Box<dyn Error>> {
+        let mut data = vec![];
+        input.read_to_end(&mut data)?;
+        let mut parser = PnmParser::new(&data);
+
+        let magic = parser.next_token("magic number")?;
+        let (n_channels, binary) = match magic {
+            b"P2" => (1, false),
+            b"P3" => (3, false),
+            b"P5" => (1, true),
+            b"P6" => (3, true),
+            _ => {
+                return Err(format!(
+                    "not a PPM/PGM image (magic number {:?})",
+                    String::from_utf8_lossy(magic)
+                )
+                .into())
+            }
+        };
+        let width = parser.next_u32("width")?;
+        let height = parser.next_u32("height")?;
+        let max_value = parser.next_u32("maximum value")?;
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return Err(format!("invalid image size {width}x{height}").into());
+        }
+        if max_value == 0 || max_value > 65535 {
+            return Err(format!("invalid maximum value {max_value} (must be 1..=65535)").into());
+        }
+
+        let n_samples = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|n| n.checked_mul(n_channels))
+            .ok_or("image too large")?;
+        let samples = if binary {
+            parser.read_binary_samples(n_samples, max_value)?
+        } else {
+            (0..n_samples)
+                .map(|_| parser.next_u32("sample"))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mut framebuffer = FrameBuffer::new(width as i32, height as i32);
+        for (k, pixel) in samples.chunks(n_channels).enumerate() {
+            let mut rgb = [0.0; 3];
+            for (c, &sample) in rgb.iter_mut().zip(pixel.iter().cycle()) {
+                if sample > max_value {
+                    return Err(format!("sample {sample} exceeds maximum value {max_value}").into());
+                }
+                *c = self.to_linear(sample as f64 / max_value as f64);
+            }
+            let (i, j) = ((k % width as usize) as i32, (k / width as usize) as i32);
+            framebuffer.set_pixel(i, j, &Color::from_slice(rgb));
+        }
+
+        Ok(framebuffer)
+    }
+}
+
+impl PpmReader {
+    fn to_linear(&self, c: f64) -> f64 {
+        match self.gamma {
+            Some(gamma) => c.powf(1.0 / gamma),
+            None => c,
+        }
+    }
+}
+
+/// tokenizer of the netpbm (and PFM) header: whitespace separated, `#` starts a comment
+pub(crate) struct PnmParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PnmParser<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while self.pos < self.data.len() {
+            match self.data[self.pos] {
+                b'#' => {
+                    while self.pos < self.data.len() && self.data[self.pos] != b'\n' {
+                        self.pos += 1;
+                    }
+                }
+                c if c.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    pub(crate) fn next_token(&mut self, what: &str) -> Result<&'a [u8], Box<dyn Error>> {
+        self.skip_whitespace_and_comments();
+        let start = self.pos;
+        while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(format!("unexpected end of data while reading {what}").into());
+        }
+
+        Ok(&self.data[start..self.pos])
+    }
+
+    pub(crate) fn next_u32(&mut self, what: &str) -> Result<u32, Box<dyn Error>> {
+        self.next_number(what)
+    }
+
+    pub(crate) fn next_f64(&mut self, what: &str) -> Result<f64, Box<dyn Error>> {
+        self.next_number(what)
+    }
+
+    fn next_number<T: std::str::FromStr>(&mut self, what: &str) -> Result<T, Box<dyn Error>> {
+        let token = self.next_token(what)?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|s| s.parse::<T>().ok())
+            .ok_or_else(|| format!("invalid {what} {:?}", String::from_utf8_lossy(token)).into())
+    }
+
+    /// binary data after the header: a single whitespace separates them
+    pub(crate) fn binary_data(&mut self, what: &str) -> Result<&'a [u8], Box<dyn Error>> {
+        match self.data.get(self.pos) {
+            Some(c) if c.is_ascii_whitespace() => self.pos += 1,
+            _ => return Err(format!("missing whitespace after {what}").into()),
+        }
+
+        Ok(&self.data[self.pos..])
+    }
+
+    /// samples of P5/P6: 1 byte each, or 2 bytes big endian if `max_value` > 255
+    fn read_binary_samples(
+        &mut self,
+        n_samples: usize,
+        max_value: u32,
+    ) -> Result<Vec<u32>, Box<dyn Error>> {
+        let bytes = self.binary_data("maximum value")?;
+        let bytes_per_sample = if max_value > 255 { 2 } else { 1 };
+        let n_bytes = n_samples
+            .checked_mul(bytes_per_sample)
+            .ok_or("image too large")?;
+        if bytes.len() < n_bytes {
+            return Err(format!(
+                "unexpected end of data: {} bytes of samples, expected {n_bytes}",
+                bytes.len()
+            )
+            .into());
+        }
+
+        Ok(bytes
+            .chunks_exact(bytes_per_sample)
+            .take(n_samples)
+            .map(|b| match b {
+                [hi, lo] => u16::from_be_bytes([*hi, *lo]) as u32,
+                _ => b[0] as u32,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_writer::{ImageWriter, PgmWriter, PpmWriter};
+
+    fn framebuffer_3x2() -> FrameBuffer {
+        let mut framebuffer = FrameBuffer::new(3, 2);
+        for j in 0..2 {
+            for i in 0..3 {
+                let c = (i + 3 * j) as f64 / 6.0;
+                framebuffer.set_pixel(i, j, &Color::from_slice([c, 1.0 - c, 0.5 * c]));
+            }
+        }
+        framebuffer
+    }
+
+    #[test]
+    fn test_ppm_round_trip() {
+        for (binary, max_value) in [(false, 255), (true, 255), (false, 65535), (true, 65535)] {
+            let mut writer = PpmWriter::new_gamma(0.5);
+            writer.binary = binary;
+            writer.max_value = max_value;
+            let mut out = vec![];
+            writer.write(&framebuffer_3x2(), &mut out).unwrap();
+
+            let framebuffer = PpmReader::new_gamma(0.5).read(&mut &out[..]).unwrap();
+            assert_eq!((framebuffer.width(), framebuffer.height()), (3, 2));
+            let mut out_2 = vec![];
+            writer.write(&framebuffer, &mut out_2).unwrap();
+            assert_eq!(out, out_2);
+
+            let tolerance = 2.0 / max_value as f64;
+            for (pixel, expected) in framebuffer.pixels().zip(framebuffer_3x2().pixels()) {
+                assert!((pixel - expected).norm() < tolerance);
+            }
+        }
+
+        let mut out = vec![];
+        PgmWriter::new()
+            .write(&framebuffer_3x2(), &mut out)
+            .unwrap();
+        let framebuffer = PpmReader::new().read(&mut &out[..]).unwrap();
+        let pixel = framebuffer.pixel(2, 1);
+        assert_eq!(pixel.x(), pixel.z());
+    }
+
+    #[test]
+    fn test_ppm_reader_errors() {
+        for (data, message) in [
+            (
+                &b""[..],
+                "unexpected end of data while reading magic number",
+            ),
+            (b"P7\n1 1\n255\n", "not a PPM/PGM image"),
+            (b"P3\n# comment\n2 x\n255\n", "invalid height \"x\""),
+            (b"P3\n0 1\n255\n", "invalid image size 0x1"),
+            (b"P3\n1 1\n70000\n0 0 0\n", "invalid maximum value 70000"),
+            (
+                b"P3\n1 1\n255\n0 0 256\n",
+                "sample 256 exceeds maximum value 255",
+            ),
+            (
+                b"P3\n1 1\n255\n0 0\n",
+                "unexpected end of data while reading sample",
+            ),
+            (
+                b"P6\n2 1\n255\n\x00\x00\x00",
+                "unexpected end of data: 3 bytes",
+            ),
+            (b"P6\n1 1\n255", "missing whitespace after maximum value"),
+            (b"P6\n2147483647 2147483647\n65535\n\x00", "image too large"),
+            (
+                b"P5\n100000 100000\n255\n\x00",
+                "unexpected end of data: 1 bytes",
+            ),
+        ] {
+            let error = PpmReader::new().read(&mut &data[..]).unwrap_err();
+            assert!(
+                error.to_string().starts_with(message),
+                "{error} does not start with {message}"
+            );
+        }
+    }
+}
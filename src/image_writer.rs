@@ -0,0 +1,269 @@
+use crate::color::{luminance, Color};
+use crate::framebuffer::FrameBuffer;
+use crate::hdr::HdrWriter;
+use crate::pfm::PfmWriter;
+use crate::png::PngWriter;
+use crate::tone_map::ToneMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// serializes a framebuffer into an image format
+pub trait ImageWriter {
+    fn write(&self, framebuffer: &FrameBuffer, out: &mut dyn Write) -> Result<(), Box<dyn Error>>;
+
+    /// write `framebuffer` to the file `file_name`
+    fn save(&self, framebuffer: &FrameBuffer, file_name: &str) -> Result<(), Box<dyn Error>> {
+        let mut out = BufWriter::new(File::create(file_name)?);
+        self.write(framebuffer, &mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// writer chosen by the extension of `file_name`: *.ppm, *.pgm, *.png (8-bit),
+/// or *.hdr, *.pfm (linear HDR, `tone_map` is ignored)
+pub fn writer_for_file(
+    file_name: &str,
+    tone_map: &ToneMap,
+) -> Result<Box<dyn ImageWriter>, Box<dyn Error>> {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("ppm") => Ok(Box::new(PpmWriter {
+            tone_map: tone_map.clone(),
+            ..PpmWriter::new()
+        })),
+        Some("pgm") => Ok(Box::new(PgmWriter {
+            tone_map: tone_map.clone(),
+            ..PgmWriter::new()
+        })),
+        Some("png") => Ok(Box::new(PngWriter {
+            tone_map: tone_map.clone(),
+            ..PngWriter::new()
+        })),
+        Some("hdr") => Ok(Box::new(HdrWriter::new())),
+        Some("pfm") => Ok(Box::new(PfmWriter)),
+        _ => Err(
+            format!("{file_name}: unknown image format (use .ppm, .pgm, .png, .hdr or .pfm)")
+                .into(),
+        ),
+    }
+}
+
+/// write `framebuffer` to `file_name` in the format given by its extension
+pub fn save_image(
+    framebuffer: &FrameBuffer,
+    file_name: &str,
+    tone_map: &ToneMap,
+) -> Result<(), Box<dyn Error>> {
+    writer_for_file(file_name, tone_map)?.save(framebuffer, file_name)
+}
+
+/// write *.ppm image: binary (P6) or ASCII (P3), 8-bit or 16-bit
+pub struct PpmWriter {
+    /// conversion of the HDR colors to samples
+    pub tone_map: ToneMap,
+    /// binary (P6), false for ASCII (P3)
+    pub binary: bool,
+    /// maximum sample value: 255 (8-bit) or 65535 (16-bit)
+    pub max_value: u16,
+}
+
+impl Default for PpmWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PpmWriter {
+    /// binary 8-bit, colors as they are (by `write_color`)
+    pub fn new() -> Self {
+        Self {
+            tone_map: ToneMap::new(),
+            binary: true,
+            max_value: 255,
+        }
+    }
+
+    /// binary 8-bit with gamma correction (by `write_color_gamma`)
+    pub fn new_gamma(gamma: f64) -> Self {
+        Self {
+            tone_map: ToneMap::new_gamma(gamma),
+            ..Self::new()
+        }
+    }
+}
+
+impl ImageWriter for PpmWriter {
+    fn write(&self, framebuffer: &FrameBuffer, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let magic = if self.binary { "P6" } else { "P3" };
+        write_pnm(
+            framebuffer,
+            out,
+            magic,
+            self.binary,
+            self.max_value,
+            |color| self.tone_map.encode(color, self.max_value),
+        )
+    }
+}
+
+/// write *.pgm grayscale image (luminance of the colors): binary (P5) or ASCII (P2)
+pub struct PgmWriter {
+    /// conversion of the HDR luminance to samples
+    pub tone_map: ToneMap,
+    /// binary (P5), false for ASCII (P2)
+    pub binary: bool,
+    /// maximum sample value: 255 (8-bit) or 65535 (16-bit)
+    pub max_value: u16,
+}
+
+impl Default for PgmWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PgmWriter {
+    /// binary 8-bit, luminance as it is
+    pub fn new() -> Self {
+        Self {
+            tone_map: ToneMap::new(),
+            binary: true,
+            max_value: 255,
+        }
+    }
+
+    /// binary 8-bit with gamma correction
+    pub fn new_gamma(gamma: f64) -> Self {
+        Self {
+            tone_map: ToneMap::new_gamma(gamma),
+            ..Self::new()
+        }
+    }
+}
+
+impl ImageWriter for PgmWriter {
+    fn write(&self, framebuffer: &FrameBuffer, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let magic = if self.binary { "P5" } else { "P2" };
+        write_pnm(
+            framebuffer,
+            out,
+            magic,
+            self.binary,
+            self.max_value,
+            |color| {
+                let y = luminance(color);
+                [self
+                    .tone_map
+                    .encode(&Color::from_slice([y, y, y]), self.max_value)[0]]
+            },
+        )
+    }
+}
+
+/// write header and samples of a netpbm image, `samples` gives the samples of one pixel
+fn write_pnm<const N: usize>(
+    framebuffer: &FrameBuffer,
+    out: &mut dyn Write,
+    magic: &str,
+    binary: bool,
+    max_value: u16,
+    samples: impl Fn(&Color) -> [u16; N],
+) -> Result<(), Box<dyn Error>> {
+    if max_value == 0 {
+        return Err("maximum sample value must be at least 1".into());
+    }
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+
+    if binary {
+        // exactly one whitespace must follow the maximum value
+        std::write!(out, "{magic}\n{width} {height}\n{max_value}\n")?;
+        let mut row = Vec::with_capacity(width.max(0) as usize * N * 2);
+        for j in 0..height {
+            row.clear();
+            for i in 0..width {
+                for sample in samples(&framebuffer.pixel(i, j)) {
+                    if max_value > 255 {
+                        row.extend_from_slice(&sample.to_be_bytes());
+                    } else {
+                        row.push(sample as u8);
+                    }
+                }
+            }
+            out.write_all(&row)?;
+        }
+    } else {
+        let header = format!("{magic}\n{width} {height}\n{max_value}\n");
+        std::writeln!(out, "{header}")?;
+        for pixel_color in framebuffer.pixels() {
+            let line = samples(&pixel_color).map(|sample| sample.to_string());
+            std::writeln!(out, "{}", line.join(" "))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framebuffer_2x1() -> FrameBuffer {
+        let mut framebuffer = FrameBuffer::new(2, 1);
+        framebuffer.set_pixel(0, 0, &Color::from_slice([1.0, 0.5, 0.0]));
+        framebuffer.set_pixel(1, 0, &Color::from_slice([0.25, 0.25, 0.25]));
+        framebuffer
+    }
+
+    #[test]
+    fn test_ppm_writer() {
+        let mut writer = PpmWriter::new_gamma(0.5);
+        writer.binary = false;
+        let mut out = vec![];
+        writer.write(&framebuffer_2x1(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "P3\n2 1\n255\n\n255 181 0\n128 128 128\n"
+        );
+
+        let mut writer = PpmWriter::new();
+        writer.max_value = 65535;
+        let mut out = vec![];
+        writer.write(&framebuffer_2x1(), &mut out).unwrap();
+        let header = b"P6\n2 1\n65535\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(
+            &out[header.len()..],
+            &[255, 255, 127, 255, 0, 0, 63, 255, 63, 255, 63, 255]
+        );
+
+        let mut out = vec![];
+        PgmWriter::new()
+            .write(&framebuffer_2x1(), &mut out)
+            .unwrap();
+        assert_eq!(out, b"P5\n2 1\n255\n\x91\x3f");
+    }
+
+    #[test]
+    fn test_writer_for_file() {
+        for (file_name, magic) in [
+            ("out.ppm", &b"P6\n"[..]),
+            ("dir.png/out.PGM", b"P5\n"),
+            ("out.png", b"\x89PNG"),
+            ("out.hdr", b"#?RADIANCE"),
+            ("out.pfm", b"PF\n"),
+        ] {
+            let mut out = vec![];
+            let writer = writer_for_file(file_name, &ToneMap::new_gamma(0.5)).unwrap();
+            writer.write(&framebuffer_2x1(), &mut out).unwrap();
+            assert_eq!(&out[..magic.len()], magic);
+        }
+        assert!(writer_for_file("out.jpg", &ToneMap::new()).is_err());
+        assert!(writer_for_file("out", &ToneMap::new()).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::aabb::AaBb;
+use crate::animated_transform::AnimatedTransform;
+use crate::hittable_material::{HitRecordMat, HittableAaBb, HittableMat};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// object space to world space, fixed or changing with the ray time
+enum Placement {
+    Fixed(Transform),
+    Animated(AnimatedTransform),
+}
+
+/// `object` placed in the world by a transform (object space to world space)
+///
+/// the object is shared, so one mesh can be placed many times.
+pub struct Instance<T: ?Sized = dyn HittableAaBb> {
+    pub object: Arc<T>,
+    placement: Placement,
+    /// replaces the material of the object (None: the object's own material)
+    pub mat: Option<Arc<dyn Material>>,
+}
+
+impl<T: ?Sized> Instance<T> {
+    pub fn new(object: Arc<T>, transform: Transform) -> Self {
+        Self::new_with_material(object, transform, None)
+    }
+
+    pub fn new_with_material(
+        object: Arc<T>,
+        transform: Transform,
+        mat: Option<Arc<dyn Material>>,
+    ) -> Self {
+        Self {
+            object,
+            placement: Placement::Fixed(transform),
+            mat,
+        }
+    }
+
+    /// moving with the ray time (motion blur)
+    pub fn new_animated(object: Arc<T>, motion: AnimatedTransform) -> Self {
+        Self {
+            object,
+            placement: Placement::Animated(motion),
+            mat: None,
+        }
+    }
+
+    /// object space to world space at `time`
+    pub fn transform(&self, time: f64) -> Cow<'_, Transform> {
+        match &self.placement {
+            Placement::Fixed(transform) => Cow::Borrowed(transform),
+            Placement::Animated(motion) => Cow::Owned(motion.transform(time)),
+        }
+    }
+
+    /// the ray in object space (the direction is not normalized, so t is the same)
+    fn object_ray(&self, ray: &Ray, transform: &Transform) -> Ray {
+        let inverse = transform.inverse();
+        Ray::from_origin_dir_tm(
+            &inverse.point(ray.origin()),
+            &inverse.vector(ray.direction()),
+            ray.time(),
+        )
+    }
+
+    /// hit point and normal back to world space
+    fn to_world(&self, rec: &mut HitRecordMat, transform: &Transform) {
+        rec.p = transform.point(&rec.p);
+        rec.normal = Vec3::new_unit_vec(transform.normal(&rec.normal));
+        if self.mat.is_some() {
+            rec.mat = self.mat.clone();
+        }
+    }
+}
+
+impl<T: HittableAaBb + ?Sized> HittableAaBb for Instance<T> {
+    fn hit_aabb(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        let transform = self.transform(ray.time());
+        if !self
+            .object
+            .hit_aabb(&self.object_ray(ray, &transform), ray_t, rec)
+        {
+            return false;
+        }
+        self.to_world(rec, &transform);
+
+        true
+    }
+
+    fn bounding_box(&self) -> AaBb {
+        let bbox = self.object.bounding_box();
+        match &self.placement {
+            Placement::Fixed(transform) => transform.aabb(&bbox),
+            Placement::Animated(motion) => motion.aabb(&bbox),
+        }
+    }
+}
+
+impl<T: HittableMat + ?Sized> HittableMat for Instance<T> {
+    fn hit_mat(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        let transform = self.transform(ray.time());
+        if !self
+            .object
+            .hit_mat(&self.object_ray(ray, &transform), ray_t, rec)
+        {
+            return false;
+        }
+        self.to_world(rec, &transform);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::consts;
+    use crate::sphere_aabb::SphereAaBb;
+    use crate::sphere_material::SphereMat;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn test_instance_hit() {
+        let unit_sphere = Arc::new(SphereAaBb::new_stationary(&Point3::new(), 1.0, None));
+        // an ellipsoid with radii 2, 1, 1 around (0, 0, -5)
+        let instance: Instance = Instance::new(
+            unit_sphere,
+            Transform::translate(&Vec3::from_slice([0.0, 0.0, -5.0]))
+                * Transform::scale(&Vec3::from_slice([2.0, 1.0, 1.0])),
+        );
+
+        let bbox = instance.bounding_box();
+        assert_eq!((bbox.x.min, bbox.x.max), (-2.0, 2.0));
+        assert_eq!((bbox.z.min, bbox.z.max), (-6.0, -4.0));
+
+        let mut rec = HitRecordMat::new();
+        let ray = Ray::from_origin_dir(&Point3::new(), &Vec3::from_slice([0.0, 0.0, -1.0]));
+        assert!(instance.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+        assert!((rec.t - 4.0).abs() < 1e-12);
+        assert!((rec.p.clone() - Point3::from_slice([0.0, 0.0, -4.0])).norm() < 1e-12);
+        assert!(rec.front_face);
+
+        // on the ellipse x^2 / 4 + z^2 = 1 the normal is (x / 4, 0, z) normalized
+        let ray = Ray::from_origin_dir(
+            &Point3::from_slice([3f64.sqrt(), 0.0, 0.0]),
+            &Vec3::from_slice([0.0, 0.0, -1.0]),
+        );
+        assert!(instance.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+        assert!((rec.t - 4.5).abs() < 1e-12);
+        let expected = Vec3::new_unit_vec(Vec3::from_slice([3f64.sqrt() / 4.0, 0.0, 0.5]));
+        assert!((rec.normal.clone() - expected).norm() < 1e-12);
+
+        // without a bounding box
+        let instance = Instance::new(
+            Arc::new(SphereMat::new(&Point3::new(), 1.0, None)),
+            Transform::rotate_x(30.0) * Transform::translate(&Vec3::from_slice([0.0, 0.0, -5.0])),
+        );
+        assert!(instance.hit_mat(
+            &Ray::from_origin_dir(
+                &Point3::new(),
+                &Transform::rotate_x(30.0).vector(&Vec3::from_slice([0.0, 0.0, -1.0]))
+            ),
+            consts::UNIVERSE,
+            &mut rec,
+        ));
+        assert!((rec.t - 4.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_animated_instance_hit() {
+        use crate::animated_transform::{AnimatedTransform, Keyframe};
+        use crate::utl;
+
+        let mut random = utl::Random::new_by_seed(23);
+
+        // a linear move is the same as a moving sphere
+        let (center_1, center_2) = (
+            Point3::from_slice([0.0, 0.0, -5.0]),
+            Point3::from_slice([1.0, 0.5, -5.0]),
+        );
+        let moving = SphereAaBb::new_moving(&center_1, &center_2, 1.0, None);
+        let mut keyframes = [Keyframe::new(0.0), Keyframe::new(1.0)];
+        keyframes[0].translation = center_1;
+        keyframes[1].translation = center_2;
+        let instance = Instance::new_animated(
+            Arc::new(SphereAaBb::new_stationary(&Point3::new(), 1.0, None)),
+            AnimatedTransform::new(keyframes.to_vec()).unwrap(),
+        );
+        let (bbox, moving_bbox) = (instance.bounding_box(), moving.bounding_box());
+        for axis in 0..3 {
+            let (a, b) = (bbox.axis_interval(axis), moving_bbox.axis_interval(axis));
+            assert!((a.min - b.min).abs() < 1e-12 && (a.max - b.max).abs() < 1e-12);
+        }
+
+        for _ in 0..1000 {
+            let ray = Ray::from_origin_dir_tm(
+                &Point3::new(),
+                &Vec3::from_slice([
+                    random.random_f64_range(-0.3, 0.5),
+                    random.random_f64_range(-0.3, 0.4),
+                    -1.0,
+                ]),
+                random.random_f64(),
+            );
+            let (mut rec_instance, mut rec_moving) = (HitRecordMat::new(), HitRecordMat::new());
+            let hit = instance.hit_aabb(&ray, consts::UNIVERSE, &mut rec_instance);
+            assert_eq!(
+                hit,
+                moving.hit_aabb(&ray, consts::UNIVERSE, &mut rec_moving)
+            );
+            if hit {
+                assert!((rec_instance.t - rec_moving.t).abs() < 1e-9);
+                assert!((rec_instance.normal.clone() - rec_moving.normal.clone()).norm() < 1e-9);
+            }
+        }
+    }
+}
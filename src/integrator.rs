@@ -0,0 +1,340 @@
+use crate::background::Background;
+use crate::color::Color;
+use crate::hittable::{HitRecord, HittableV2};
+use crate::hittable_material::{HitRecordMat, HittableMat};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::utl;
+use crate::vec3::Vec3;
+
+/// computes the color seen along a ray in world `W`, rays that hit nothing see `background`
+pub trait Integrator<W: ?Sized>: Sync {
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        depth: i32,
+        world: &W,
+        background: &dyn Background,
+        random: &mut utl::Random,
+    ) -> Color;
+}
+
+/// visualize surface normals
+pub struct NormalIntegrator;
+
+impl<W: HittableV2> Integrator<W> for NormalIntegrator {
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        _depth: i32,
+        world: &W,
+        background: &dyn Background,
+        _random: &mut utl::Random,
+    ) -> Color {
+        let mut rec = HitRecord::new();
+        if world.hit_v2(
+            ray,
+            Interval::new_by_value(0.0, utl::constans::INFINITY),
+            &mut rec,
+        ) {
+            return 0.5 * (rec.normal.clone() + Color::from_slice([1.0, 1.0, 1.0]));
+        }
+
+        background.color(ray)
+    }
+}
+
+/// diffuse reflection uniformly distributed on the hemisphere, 50% reflectance
+pub struct HemisphereIntegrator {
+    /// rays start at `t_min` to avoid shadow acne (0.0 reproduces it)
+    pub t_min: f64,
+}
+
+impl HemisphereIntegrator {
+    pub fn new(t_min: f64) -> Self {
+        Self { t_min }
+    }
+}
+
+impl<W: HittableV2> Integrator<W> for HemisphereIntegrator {
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        depth: i32,
+        world: &W,
+        background: &dyn Background,
+        random: &mut utl::Random,
+    ) -> Color {
+        if depth <= 0 {
+            return Color::from_slice([0.0, 0.0, 0.0]);
+        }
+
+        let mut rec = HitRecord::new();
+        if world.hit_v2(
+            ray,
+            Interval::new_by_value(self.t_min, utl::constans::INFINITY),
+            &mut rec,
+        ) {
+            let direction = Vec3::random_on_hemisphere(&rec.normal, random);
+            return 0.5
+                * self.ray_color(
+                    &Ray::from_origin_dir(&rec.p, &direction),
+                    depth - 1,
+                    world,
+                    background,
+                    random,
+                );
+        }
+
+        background.color(ray)
+    }
+}
+
+/// Lambertian (cosine weighted) diffuse reflection, 50% reflectance
+pub struct LambertianIntegrator;
+
+impl<W: HittableV2> Integrator<W> for LambertianIntegrator {
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        depth: i32,
+        world: &W,
+        background: &dyn Background,
+        random: &mut utl::Random,
+    ) -> Color {
+        if depth <= 0 {
+            return Color::from_slice([0.0, 0.0, 0.0]);
+        }
+
+        let mut rec = HitRecord::new();
+        if world.hit_v2(
+            ray,
+            Interval::new_by_value(0.001, utl::constans::INFINITY),
+            &mut rec,
+        ) {
+            let direction = rec.normal.clone() + Vec3::random_unit_vector(random);
+            return 0.5
+                * self.ray_color(
+                    &Ray::from_origin_dir(&rec.p, &direction),
+                    depth - 1,
+                    world,
+                    background,
+                    random,
+                );
+        }
+
+        background.color(ray)
+    }
+}
+
+/// path tracing with the materials of the hit objects, including their emission
+pub struct MaterialIntegrator;
+
+impl<W: HittableMat> Integrator<W> for MaterialIntegrator {
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        depth: i32,
+        world: &W,
+        background: &dyn Background,
+        random: &mut utl::Random,
+    ) -> Color {
+        if depth <= 0 {
+            return Color::from_slice([0.0, 0.0, 0.0]);
+        }
+
+        let mut rec = HitRecordMat::new();
+        if world.hit_mat(
+            ray,
+            Interval::new_by_value(0.001, utl::constans::INFINITY),
+            &mut rec,
+        ) {
+            let mat = rec.mat.clone().unwrap();
+            let color_from_emission = mat.emitted(ray, &rec);
+
+            let mut scatterd = Ray::new();
+            let mut attenuation = Color::new();
+            if mat.scatter(ray, &rec, &mut attenuation, &mut scatterd, random) {
+                return color_from_emission
+                    + attenuation
+                        * self.ray_color(&scatterd, depth - 1, world, background, random);
+            }
+            return color_from_emission;
+        }
+
+        background.color(ray)
+    }
+}
+
+/// path tracing that also samples the background directly at diffuse hits (next event
+/// estimation), both strategies are combined by multiple importance sampling
+pub struct MisIntegrator;
+
+impl<W: HittableMat> Integrator<W> for MisIntegrator {
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        depth: i32,
+        world: &W,
+        background: &dyn Background,
+        random: &mut utl::Random,
+    ) -> Color {
+        let mut color = Color::new();
+        let mut throughput = Color::from_slice([1.0, 1.0, 1.0]);
+        let mut ray = Ray::from_origin_dir_tm(ray.origin(), ray.direction(), ray.time());
+        // scattering density of `ray` if the background was also sampled at its origin
+        let mut scattering_pdf: Option<f64> = None;
+
+        for bounce in 0..depth {
+            let mut rec = HitRecordMat::new();
+            if !world.hit_mat(
+                &ray,
+                Interval::new_by_value(0.001, utl::constans::INFINITY),
+                &mut rec,
+            ) {
+                let weight = match scattering_pdf {
+                    Some(pdf) => power_heuristic(pdf, background.pdf(ray.direction())),
+                    None => 1.0,
+                };
+                color += weight * throughput.clone() * background.color(&ray);
+                break;
+            }
+
+            let mat = rec.mat.clone().unwrap();
+            color += throughput.clone() * mat.emitted(&ray, &rec);
+
+            let mut scatterd = Ray::new();
+            let mut attenuation = Color::new();
+            if !mat.scatter(&ray, &rec, &mut attenuation, &mut scatterd, random) {
+                break;
+            }
+
+            scattering_pdf = None;
+            let pdf = mat.scattering_pdf(&ray, &rec, &scatterd);
+            if pdf > 0.0 && bounce + 1 < depth {
+                if let Some((direction, light_pdf)) = background.sample(random) {
+                    let light_ray = Ray::from_origin_dir_tm(&rec.p, &direction, ray.time());
+                    let light_scattering_pdf = mat.scattering_pdf(&ray, &rec, &light_ray);
+                    let mut shadow_rec = HitRecordMat::new();
+                    if light_scattering_pdf > 0.0
+                        && !world.hit_mat(
+                            &light_ray,
+                            Interval::new_by_value(0.001, utl::constans::INFINITY),
+                            &mut shadow_rec,
+                        )
+                    {
+                        let weight = power_heuristic(light_pdf, light_scattering_pdf);
+                        color += (weight * light_scattering_pdf / light_pdf)
+                            * throughput.clone()
+                            * attenuation.clone()
+                            * background.color(&light_ray);
+                    }
+                }
+                scattering_pdf = Some(pdf);
+            }
+
+            throughput = throughput * attenuation;
+            ray = scatterd;
+        }
+
+        color
+    }
+}
+
+/// weight of a sample with density `pdf` when another strategy has density `other_pdf`
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::GradientBackground;
+    use crate::environment_map::EnvironmentMap;
+    use crate::hittable_list::HittableList;
+    use crate::material::{DiffuseLight, Lambertian, Material};
+    use crate::sphere_material::SphereMat;
+    use crate::vec3::Point3;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_emission() {
+        let emit = Color::from_slice([4.0, 2.0, 1.0]);
+        let light: Option<Arc<dyn Material>> = Some(Arc::new(DiffuseLight::new(&emit)));
+        let white: Option<Arc<dyn Material>> =
+            Some(Arc::new(Lambertian::new(&Color::from_slice([
+                0.5, 0.5, 0.5,
+            ]))));
+
+        // a light inside a closed white sphere: no light from the sky reaches the camera
+        let mut world = HittableList::new();
+        world.add(Arc::new(SphereMat::new(&Point3::new(), 10.0, white)));
+        world.add(Arc::new(SphereMat::new(
+            &Point3::from_slice([0.0, 0.0, -5.0]),
+            1.0,
+            light,
+        )));
+
+        let sky = GradientBackground::new();
+        let mut random = utl::Random::new_by_seed(1);
+        let to_light = Ray::from_origin_dir(&Point3::new(), &Vec3::from_slice([0.0, 0.0, -1.0]));
+        let color = MaterialIntegrator.ray_color(&to_light, 10, &world, &sky, &mut random);
+        assert_eq!(color.e, emit.e);
+
+        let to_wall = Ray::from_origin_dir(&Point3::new(), &Vec3::from_slice([0.0, 0.0, 1.0]));
+        let mut sum = Color::new();
+        for _ in 0..1000 {
+            sum += MaterialIntegrator.ray_color(&to_wall, 10, &world, &sky, &mut random);
+        }
+        // only the light contributes: the ratio of the channels is kept
+        assert!(sum.x() > 0.0);
+        assert!((sum.y() / sum.x() - 0.5).abs() < 1e-9);
+        assert!((sum.z() / sum.x() - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mis_furnace() {
+        // a white environment: a convex diffuse object reflects its albedo
+        let mut image = crate::framebuffer::FrameBuffer::new(8, 4);
+        for j in 0..4 {
+            for i in 0..8 {
+                image.set_pixel(i, j, &Color::from_slice([1.0, 1.0, 1.0]));
+            }
+        }
+        let environment = EnvironmentMap::new(&image);
+        let gray: Option<Arc<dyn Material>> =
+            Some(Arc::new(Lambertian::new(&Color::from_slice([
+                0.5, 0.5, 0.5,
+            ]))));
+        let mut world = HittableList::new();
+        world.add(Arc::new(SphereMat::new(&Point3::new(), 1.0, gray)));
+
+        let mut random = utl::Random::new_by_seed(3);
+        let to_sphere = Ray::from_origin_dir(
+            &Point3::from_slice([0.0, 0.0, 5.0]),
+            &Vec3::from_slice([0.0, 0.0, -1.0]),
+        );
+        let n = 4000;
+        let mut sum = Color::new();
+        for _ in 0..n {
+            sum += MisIntegrator.ray_color(&to_sphere, 10, &world, &environment, &mut random);
+        }
+        let mean = sum.x() / n as f64;
+        assert!((mean - 0.5).abs() < 0.01, "{mean}");
+
+        // without direct sampling the result is the same as MaterialIntegrator
+        let sky = GradientBackground::new();
+        let mut a = utl::Random::new_by_seed(5);
+        let mut b = utl::Random::new_by_seed(5);
+        for _ in 0..10 {
+            let mis = MisIntegrator.ray_color(&to_sphere, 10, &world, &sky, &mut a);
+            let material = MaterialIntegrator.ray_color(&to_sphere, 10, &world, &sky, &mut b);
+            assert!((mis - material).norm() < 1e-12);
+        }
+    }
+}
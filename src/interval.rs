@@ -6,6 +6,12 @@ pub struct Interval {
     pub max: f64,
 }
 
+impl Default for Interval {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Interval {
     pub fn new() -> Self {
         Interval {
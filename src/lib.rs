@@ -1,17 +1,40 @@
 pub mod aabb;
+pub mod animated_transform;
+pub mod background;
 pub mod bvh;
+pub mod bvh_linear;
 pub mod camera;
+pub mod camera_path;
 pub mod color;
+pub mod environment_map;
+pub mod framebuffer;
+pub mod hdr;
 pub mod hittable;
 pub mod hittable_list;
 pub mod hittable_list_aabb;
 pub mod hittable_material;
+pub mod image_reader;
+pub mod image_writer;
+pub mod instance;
+pub mod integrator;
 pub mod interval;
 pub mod material;
+pub mod obj;
+pub mod pfm;
+pub mod ply;
+pub mod png;
+pub mod quad;
 pub mod ray;
 pub mod sphere;
 pub mod sphere_aabb;
 pub mod sphere_material;
 pub mod sphere_moving;
+pub mod stl;
+pub mod tone_map;
+pub mod top_level_bvh;
+pub mod transform;
+pub mod triangle;
+pub mod triangle_mesh;
 pub mod utl;
 pub mod vec3;
+pub mod zlib;
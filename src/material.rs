@@ -4,16 +4,28 @@ use crate::ray::Ray;
 use crate::utl;
 use crate::vec3::Vec3;
 
-pub trait Material {
+pub trait Material: Send + Sync {
     fn scatter(
         &self,
         _r_in: &Ray,
         _rec: &HitRecordMat,
         _attennuation: &mut Color,
         _scattered: &mut Ray,
+        _random: &mut utl::Random,
     ) -> bool {
         false
     }
+
+    /// radiance emitted at the hit point (black for materials that do not emit)
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecordMat) -> Color {
+        Color::new()
+    }
+
+    /// density per solid angle of `scatter` generating `scattered`
+    /// (0 for specular materials), attenuation * pdf is the BRDF times the cosine
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecordMat, _scattered: &Ray) -> f64 {
+        0.0
+    }
 }
 
 pub struct Lambertian {
@@ -35,17 +47,67 @@ impl Material for Lambertian {
         rec: &HitRecordMat,
         attennuation: &mut Color,
         scattered: &mut Ray,
+        random: &mut utl::Random,
     ) -> bool {
-        let mut scatter_direction = rec.normal.clone() + Vec3::random_unit_vector();
+        *scattered = scatter_lambertian(r_in, rec, random);
+        *attennuation = self.albedo.clone();
+        true
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecordMat, scattered: &Ray) -> f64 {
+        lambertian_pdf(rec, scattered)
+    }
+}
+
+/// Lambertian with the interpolated vertex colors of a mesh as albedo,
+/// `albedo` where the hit object has no vertex colors
+pub struct LambertianVertexColor {
+    albedo: Color,
+}
 
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal.clone();
+impl LambertianVertexColor {
+    pub fn new(albedo: &Color) -> Self {
+        Self {
+            albedo: albedo.clone(),
         }
+    }
+}
 
-        *scattered = Ray::from_origin_dir_tm(&rec.p, &scatter_direction, r_in.time());
-        *attennuation = self.albedo.clone();
+impl Material for LambertianVertexColor {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecordMat,
+        attennuation: &mut Color,
+        scattered: &mut Ray,
+        random: &mut utl::Random,
+    ) -> bool {
+        *scattered = scatter_lambertian(r_in, rec, random);
+        *attennuation = rec.color.clone().unwrap_or(self.albedo.clone());
         true
     }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecordMat, scattered: &Ray) -> f64 {
+        lambertian_pdf(rec, scattered)
+    }
+}
+
+/// cosine weighted direction around the normal
+fn scatter_lambertian(r_in: &Ray, rec: &HitRecordMat, random: &mut utl::Random) -> Ray {
+    let mut scatter_direction = rec.normal.clone() + Vec3::random_unit_vector(random);
+
+    if scatter_direction.near_zero() {
+        scatter_direction = rec.normal.clone();
+    }
+
+    Ray::from_origin_dir_tm(&rec.p, &scatter_direction, r_in.time())
+}
+
+fn lambertian_pdf(rec: &HitRecordMat, scattered: &Ray) -> f64 {
+    let cos_theta = rec
+        .normal
+        .dot(&Vec3::new_unit_vec(scattered.direction().clone()));
+    cos_theta.max(0.0) / utl::constans::PI
 }
 
 pub struct Metal {
@@ -67,6 +129,7 @@ impl Material for Metal {
         rec: &HitRecordMat,
         attennuation: &mut Color,
         scattered: &mut Ray,
+        _random: &mut utl::Random,
     ) -> bool {
         let reflected = Vec3::reflect(r_in.direction(), &rec.normal);
         *scattered = Ray::from_origin_dir_tm(&rec.p, &reflected, r_in.time());
@@ -98,10 +161,11 @@ impl Material for MetalFuzz {
         rec: &HitRecordMat,
         attennuation: &mut Color,
         scattered: &mut Ray,
+        random: &mut utl::Random,
     ) -> bool {
         let mut reflected = Vec3::reflect(r_in.direction(), &rec.normal);
         reflected.normalize();
-        reflected += self.fuzz * Vec3::random_unit_vector();
+        reflected += self.fuzz * Vec3::random_unit_vector(random);
         *scattered = Ray::from_origin_dir_tm(&rec.p, &reflected, r_in.time());
         *attennuation = self.albedo.clone();
 
@@ -127,6 +191,7 @@ impl Material for Dielectric {
         rec: &HitRecordMat,
         attennuation: &mut Color,
         scattered: &mut Ray,
+        _random: &mut utl::Random,
     ) -> bool {
         *attennuation = Color::from_slice([1.0, 1.0, 1.0]);
         let ri = if rec.front_face {
@@ -161,6 +226,7 @@ impl Material for DielectricV2 {
         rec: &HitRecordMat,
         attennuation: &mut Color,
         scattered: &mut Ray,
+        _random: &mut utl::Random,
     ) -> bool {
         *attennuation = Color::from_slice([1.0, 1.0, 1.0]);
         let ri = if rec.front_face {
@@ -209,6 +275,7 @@ impl Material for DielectricV3 {
         rec: &HitRecordMat,
         attennuation: &mut Color,
         scattered: &mut Ray,
+        random: &mut utl::Random,
     ) -> bool {
         *attennuation = Color::from_slice([1.0, 1.0, 1.0]);
         let ri = if rec.front_face {
@@ -222,7 +289,8 @@ impl Material for DielectricV3 {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = ri * sin_theta > 1.0;
-        let direction = if cannot_refract || Self::reflectance(cos_theta, ri) > utl::random_f64() {
+        let direction = if cannot_refract || Self::reflectance(cos_theta, ri) > random.random_f64()
+        {
             Vec3::reflect(&unit_direction, &rec.normal)
         } else {
             Vec3::refract(&unit_direction, &rec.normal, ri)
@@ -232,3 +300,20 @@ impl Material for DielectricV3 {
         true
     }
 }
+
+/// area light: emits `emit` uniformly from both sides and does not scatter
+pub struct DiffuseLight {
+    emit: Color,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: &Color) -> Self {
+        Self { emit: emit.clone() }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecordMat) -> Color {
+        self.emit.clone()
+    }
+}
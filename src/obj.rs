@@ -0,0 +1,611 @@
+use crate::color::Color;
+use crate::hittable_list_aabb::HittableListAaBb;
+use crate::hittable_material::HittableAaBb;
+use crate::material::{DielectricV3, DiffuseLight, Lambertian, Material, MetalFuzz};
+use crate::triangle_mesh::TriangleMesh;
+use crate::vec3::{Point3, Vec3};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+/// triangles of one group with one material
+pub struct ObjObject {
+    /// name given by `g` or `o` ("default" before the first one)
+    pub group: String,
+    /// name given by `usemtl` (empty before the first one)
+    pub material: String,
+    pub mesh: Arc<TriangleMesh>,
+}
+
+/// geometry of a Wavefront *.obj file: one mesh for each group and material
+pub struct ObjModel {
+    pub objects: Vec<ObjObject>,
+}
+
+impl ObjModel {
+    pub fn n_triangles(&self) -> usize {
+        self.objects
+            .iter()
+            .map(|object| object.mesh.n_triangles())
+            .sum()
+    }
+
+    /// all meshes as objects of a scene
+    pub fn hittable_list(&self) -> HittableListAaBb<dyn HittableAaBb> {
+        let mut list: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+        for object in self.objects.iter() {
+            list.add(object.mesh.clone());
+        }
+
+        list
+    }
+}
+
+/// load Wavefront *.obj models with their *.mtl material libraries
+///
+/// supports `v`, `vt`, `vn`, polygon faces (triangulated as fans), negative (relative)
+/// indices, `g`, `o`, `mtllib` and `usemtl`. other statements are ignored.
+/// a material library that cannot be read or an unknown material is a warning.
+pub struct ObjLoader {
+    /// material of faces before the first `usemtl` or with an unknown material
+    pub default_material: Option<Arc<dyn Material>>,
+}
+
+impl Default for ObjLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ObjLoader {
+    /// gray Lambertian for faces without a material
+    pub fn new() -> Self {
+        Self {
+            default_material: Some(Arc::new(Lambertian::new(&Color::from_slice([
+                0.5, 0.5, 0.5,
+            ])))),
+        }
+    }
+
+    pub fn load(&self, file_name: &str) -> Result<ObjModel, Box<dyn Error>> {
+        let source = std::fs::read_to_string(file_name).map_err(|e| format!("{file_name}: {e}"))?;
+        self.parse(&source, file_name)
+    }
+
+    /// parse the contents of `file_name`, material libraries are relative to its directory
+    pub fn parse(&self, source: &str, file_name: &str) -> Result<ObjModel, Box<dyn Error>> {
+        let directory = Path::new(file_name).parent().unwrap_or(Path::new(""));
+        self.parse_with(source, file_name, &|mtl_name| {
+            let path = directory.join(mtl_name);
+            let mtl_file_name = path.to_string_lossy().into_owned();
+            let source = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            Ok((mtl_file_name, source))
+        })
+    }
+
+    fn parse_with(
+        &self,
+        source: &str,
+        file_name: &str,
+        read_mtl: &ReadMtl<'_>,
+    ) -> Result<ObjModel, Box<dyn Error>> {
+        let mut positions = vec![];
+        let mut uvs = vec![];
+        let mut normals = vec![];
+        let mut materials = HashMap::new();
+        let mut builders: Vec<MeshBuilder> = vec![];
+        let mut current: Option<usize> = None;
+        let mut group = "default".to_string();
+        let mut material_name = String::new();
+
+        for (line_index, line) in source.lines().enumerate() {
+            let error = |message: String| format!("{file_name}:{}: {message}", line_index + 1);
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let arguments = tokens.collect::<Vec<_>>();
+
+            match keyword {
+                "v" => positions.push(Point3::from_slice(
+                    parse_vector(&arguments, 3).map_err(error)?,
+                )),
+                "vn" => normals.push(Vec3::from_slice(
+                    parse_vector(&arguments, 3).map_err(error)?,
+                )),
+                "vt" => {
+                    // v and w are optional
+                    let uv = parse_numbers(&arguments).map_err(error)?;
+                    match uv[..] {
+                        [u] => uvs.push([u, 0.0]),
+                        [u, v, ..] => uvs.push([u, v]),
+                        _ => return Err(error("missing texture coordinate".to_string()).into()),
+                    }
+                }
+                "f" => {
+                    if arguments.len() < 3 {
+                        return Err(error(format!(
+                            "a face needs at least 3 vertices, found {}",
+                            arguments.len()
+                        ))
+                        .into());
+                    }
+                    let corners = arguments
+                        .iter()
+                        .map(|corner| {
+                            parse_corner(corner, positions.len(), uvs.len(), normals.len())
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(error)?;
+
+                    let builder = match current {
+                        Some(k) => &mut builders[k],
+                        None => {
+                            let mat = materials
+                                .get(&material_name)
+                                .cloned()
+                                .or_else(|| self.default_material.clone());
+                            builders.push(MeshBuilder::new(&group, &material_name, mat));
+                            current = Some(builders.len() - 1);
+                            builders.last_mut().unwrap()
+                        }
+                    };
+                    let vertices = corners
+                        .iter()
+                        .map(|corner| builder.vertex(corner, &positions, &uvs, &normals))
+                        .collect::<Vec<_>>();
+                    // fan triangulation (for convex polygons)
+                    for k in 1..vertices.len() - 1 {
+                        builder
+                            .indices
+                            .push([vertices[0], vertices[k], vertices[k + 1]]);
+                    }
+                }
+                "g" | "o" => {
+                    group = if arguments.is_empty() {
+                        "default".to_string()
+                    } else {
+                        arguments.join(" ")
+                    };
+                    current = None;
+                }
+                "usemtl" => {
+                    let name = arguments.join(" ");
+                    if !materials.contains_key(&name) {
+                        eprintln!(
+                            "{}",
+                            error(format!("unknown material {name:?}, using the default"))
+                        );
+                    }
+                    material_name = name;
+                    current = None;
+                }
+                "mtllib" => {
+                    for mtl_name in arguments {
+                        let (mtl_file_name, mtl_source) = match read_mtl(mtl_name) {
+                            Ok(mtl) => mtl,
+                            Err(e) => {
+                                eprintln!("{}", error(format!("cannot read {mtl_name}: {e}")));
+                                continue;
+                            }
+                        };
+                        for (name, mtl) in parse_mtl(&mtl_source, &mtl_file_name)? {
+                            materials.insert(name, mtl.to_material());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let objects = builders
+            .into_iter()
+            .filter(|builder| !builder.indices.is_empty())
+            .map(|builder| builder.build())
+            .collect();
+
+        Ok(ObjModel { objects })
+    }
+}
+
+/// returns the file name and the contents of the material library `name`
+type ReadMtl<'a> = dyn Fn(&str) -> Result<(String, String), String> + 'a;
+
+/// mesh of one group and material while parsing
+struct MeshBuilder {
+    group: String,
+    material: String,
+    mat: Option<Arc<dyn Material>>,
+    positions: Vec<Point3>,
+    uvs: Vec<Option<[f64; 2]>>,
+    normals: Vec<Option<Vec3>>,
+    indices: Vec<[u32; 3]>,
+    /// mesh vertex of each distinct (position, uv, normal) of the file
+    vertices: HashMap<Corner, u32>,
+}
+
+/// zero-based indices of a face corner: position, texture coordinate and normal
+type Corner = (usize, Option<usize>, Option<usize>);
+
+impl MeshBuilder {
+    fn new(group: &str, material: &str, mat: Option<Arc<dyn Material>>) -> Self {
+        Self {
+            group: group.to_string(),
+            material: material.to_string(),
+            mat,
+            positions: vec![],
+            uvs: vec![],
+            normals: vec![],
+            indices: vec![],
+            vertices: HashMap::new(),
+        }
+    }
+
+    fn vertex(
+        &mut self,
+        corner: &Corner,
+        positions: &[Point3],
+        uvs: &[[f64; 2]],
+        normals: &[Vec3],
+    ) -> u32 {
+        if let Some(&k) = self.vertices.get(corner) {
+            return k;
+        }
+        let &(position, uv, normal) = corner;
+        let k = self.positions.len() as u32;
+        self.positions.push(positions[position].clone());
+        self.uvs.push(uv.map(|uv| uvs[uv]));
+        self.normals
+            .push(normal.map(|normal| normals[normal].clone()));
+        self.vertices.insert(*corner, k);
+
+        k
+    }
+
+    /// normals and uvs are kept only when every vertex has them
+    fn build(self) -> ObjObject {
+        let normals = self.normals.into_iter().collect::<Option<Vec<_>>>();
+        let uvs = self.uvs.into_iter().collect::<Option<Vec<_>>>();
+        let mesh = TriangleMesh::new_with_attributes(
+            self.positions,
+            normals,
+            uvs,
+            None,
+            self.indices,
+            self.mat,
+        );
+
+        ObjObject {
+            group: self.group,
+            material: self.material,
+            mesh: Arc::new(mesh),
+        }
+    }
+}
+
+fn parse_numbers(arguments: &[&str]) -> Result<Vec<f64>, String> {
+    arguments
+        .iter()
+        .map(|argument| {
+            argument
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number {argument:?}"))
+        })
+        .collect()
+}
+
+/// the first `n` numbers (more are allowed, e.g. w or vertex colors)
+fn parse_vector(arguments: &[&str], n: usize) -> Result<[f64; 3], String> {
+    let numbers = parse_numbers(arguments)?;
+    if numbers.len() < n {
+        return Err(format!("expected {n} numbers, found {}", numbers.len()));
+    }
+
+    Ok([numbers[0], numbers[1], numbers[2]])
+}
+
+/// `v`, `v/vt`, `v//vn` or `v/vt/vn` with one-based or negative (relative) indices
+fn parse_corner(
+    corner: &str,
+    n_positions: usize,
+    n_uvs: usize,
+    n_normals: usize,
+) -> Result<Corner, String> {
+    let mut parts = corner.split('/');
+    let position = parse_index(parts.next().unwrap_or(""), n_positions, "vertex")?;
+    let uv = match parts.next() {
+        None | Some("") => None,
+        Some(index) => Some(parse_index(index, n_uvs, "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        None | Some("") => None,
+        Some(index) => Some(parse_index(index, n_normals, "normal")?),
+    };
+    if parts.next().is_some() {
+        return Err(format!("invalid face vertex {corner:?}"));
+    }
+
+    Ok((position, uv, normal))
+}
+
+fn parse_index(index: &str, n: usize, what: &str) -> Result<usize, String> {
+    let k = index
+        .parse::<i64>()
+        .map_err(|_| format!("invalid {what} index {index:?}"))?;
+    let resolved = if k < 0 { n as i64 + k } else { k - 1 };
+    if k == 0 || resolved < 0 || resolved >= n as i64 {
+        return Err(format!("{what} index {k} out of range ({n} defined)"));
+    }
+
+    Ok(resolved as usize)
+}
+
+/// parameters of a *.mtl material that are used
+#[derive(Debug, Clone)]
+struct MtlMaterial {
+    /// Kd
+    diffuse: Color,
+    /// Ks
+    specular: Color,
+    /// Ke
+    emission: Color,
+    /// Ns
+    shininess: f64,
+    /// Ni
+    refraction_index: f64,
+    /// d (or 1 - Tr)
+    dissolve: f64,
+    illum: u32,
+}
+
+/// how a *.mtl material is mapped to the materials of the crate
+#[derive(Debug)]
+enum MtlModel {
+    Diffuse(Color),
+    Metal(Color, f64),
+    Dielectric(f64),
+    Light(Color),
+}
+
+impl MtlMaterial {
+    fn new() -> Self {
+        Self {
+            diffuse: Color::from_slice([0.8, 0.8, 0.8]),
+            specular: Color::new(),
+            emission: Color::new(),
+            shininess: 0.0,
+            refraction_index: 1.5,
+            dissolve: 1.0,
+            illum: 2,
+        }
+    }
+
+    /// emissive: Ke is not black; dielectric: transparent or refracting illumination
+    /// model (4, 6, 7, 9); metal: reflecting illumination model (3, 5, 8) or only
+    /// specular color; Lambertian otherwise
+    fn model(&self) -> MtlModel {
+        let is_black = |color: &Color| color.e.iter().all(|&c| c <= 0.0);
+        if !is_black(&self.emission) {
+            MtlModel::Light(self.emission.clone())
+        } else if self.dissolve < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            MtlModel::Dielectric(self.refraction_index)
+        } else if matches!(self.illum, 3 | 5 | 8)
+            || (is_black(&self.diffuse) && !is_black(&self.specular))
+        {
+            // the Phong exponent as roughness
+            let fuzz = (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt();
+            MtlModel::Metal(self.specular.clone(), fuzz)
+        } else {
+            MtlModel::Diffuse(self.diffuse.clone())
+        }
+    }
+
+    fn to_material(&self) -> Arc<dyn Material> {
+        match self.model() {
+            MtlModel::Diffuse(albedo) => Arc::new(Lambertian::new(&albedo)),
+            MtlModel::Metal(albedo, fuzz) => Arc::new(MetalFuzz::new(&albedo, fuzz)),
+            MtlModel::Dielectric(refraction_index) => Arc::new(DielectricV3::new(refraction_index)),
+            MtlModel::Light(emit) => Arc::new(DiffuseLight::new(&emit)),
+        }
+    }
+}
+
+/// materials of a *.mtl material library by name
+fn parse_mtl(source: &str, file_name: &str) -> Result<Vec<(String, MtlMaterial)>, Box<dyn Error>> {
+    let mut materials: Vec<(String, MtlMaterial)> = vec![];
+
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: String| format!("{file_name}:{}: {message}", line_index + 1);
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let arguments = tokens.collect::<Vec<_>>();
+
+        if keyword == "newmtl" {
+            materials.push((arguments.join(" "), MtlMaterial::new()));
+            continue;
+        }
+        if keyword.starts_with('#') {
+            continue;
+        }
+        let Some((_, material)) = materials.last_mut() else {
+            return Err(error(format!("{keyword} before newmtl")).into());
+        };
+        let number = || -> Result<f64, String> {
+            let numbers = parse_numbers(&arguments)?;
+            numbers
+                .first()
+                .copied()
+                .ok_or_else(|| format!("missing value of {keyword}"))
+        };
+        let color = || -> Result<Color, String> {
+            // a single value is gray
+            match parse_numbers(&arguments)?[..] {
+                [c] => Ok(Color::from_slice([c, c, c])),
+                [r, g, b, ..] => Ok(Color::from_slice([r, g, b])),
+                _ => Err(format!("expected a color after {keyword}")),
+            }
+        };
+
+        match keyword {
+            "Kd" => material.diffuse = color().map_err(error)?,
+            "Ks" => material.specular = color().map_err(error)?,
+            "Ke" => material.emission = color().map_err(error)?,
+            "Ns" => material.shininess = number().map_err(error)?,
+            "Ni" => material.refraction_index = number().map_err(error)?,
+            "d" => material.dissolve = number().map_err(error)?,
+            "Tr" => material.dissolve = 1.0 - number().map_err(error)?,
+            "illum" => material.illum = number().map_err(error)? as u32,
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable_material::HitRecordMat;
+    use crate::interval::consts;
+    use crate::ray::Ray;
+
+    const MTL: &str = "# materials
+newmtl white
+Kd 0.7 0.7 0.7
+newmtl gold
+Ks 1.0 0.8 0.3
+Ns 6
+illum 3
+newmtl glass
+Ni 1.45
+d 0.1
+newmtl lamp
+Kd 0.0 0.0 0.0
+Ke 10 10 8
+";
+
+    fn read_mtl(name: &str) -> Result<(String, String), String> {
+        match name {
+            "scene.mtl" => Ok(("dir/scene.mtl".to_string(), MTL.to_string())),
+            _ => Err("not found".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_parse_obj() {
+        let source = "# a quad and a triangle
+mtllib scene.mtl
+v -1 0 -1
+v 1 0 -1
+v 1 0 1
+v -1 0 1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 1 0
+g floor
+usemtl white
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g light
+usemtl lamp
+v 0 2 0
+v 1 2 0
+v 0 2 1
+f -3 -2 -1
+";
+        let model = ObjLoader::new()
+            .parse_with(source, "dir/scene.obj", &read_mtl)
+            .unwrap();
+        assert_eq!(model.objects.len(), 2);
+        assert_eq!(model.n_triangles(), 3);
+        assert_eq!(model.objects[0].group, "floor");
+        assert_eq!(model.objects[0].material, "white");
+        assert_eq!(model.objects[0].mesh.n_vertices(), 4);
+        assert_eq!(model.objects[1].material, "lamp");
+
+        let mut rec = HitRecordMat::new();
+        let down = Ray::from_origin_dir(
+            &Point3::from_slice([0.5, 1.0, -0.5]),
+            &Vec3::from_slice([0.0, -1.0, 0.0]),
+        );
+        assert!(model
+            .hittable_list()
+            .hit_aabb(&down, consts::UNIVERSE, &mut rec));
+        assert!((rec.u - 0.75).abs() < 1e-12 && (rec.v - 0.25).abs() < 1e-12);
+        assert_eq!(rec.normal.e, [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_parse_mtl() {
+        let materials = parse_mtl(MTL, "scene.mtl").unwrap();
+        let model = |name: &str| {
+            let (_, mtl) = materials.iter().find(|(n, _)| n == name).unwrap();
+            mtl.model()
+        };
+        assert!(matches!(model("white"), MtlModel::Diffuse(c) if c.e == [0.7, 0.7, 0.7]));
+        assert!(
+            matches!(model("gold"), MtlModel::Metal(c, fuzz) if c.e == [1.0, 0.8, 0.3] && fuzz == 0.5)
+        );
+        assert!(matches!(model("glass"), MtlModel::Dielectric(ri) if ri == 1.45));
+        assert!(matches!(model("lamp"), MtlModel::Light(c) if c.e == [10.0, 10.0, 8.0]));
+    }
+
+    #[test]
+    fn test_obj_errors() {
+        for (source, message) in [
+            ("v 1 2\n", "dir/scene.obj:1: expected 3 numbers, found 2"),
+            (
+                "v 1 2 3\nf 1 1\n",
+                "dir/scene.obj:2: a face needs at least 3 vertices",
+            ),
+            (
+                "v 1 2 3\n\nf 1 2 1\n",
+                "dir/scene.obj:3: vertex index 2 out of range",
+            ),
+            (
+                "v 1 2 3\nf 1 -2 1\n",
+                "dir/scene.obj:2: vertex index -2 out of range",
+            ),
+            (
+                "v 1 2 3\nf 1/1 1 1\n",
+                "dir/scene.obj:2: texture coordinate index 1 out of range",
+            ),
+            ("vn x 0 0\n", "dir/scene.obj:1: invalid number \"x\""),
+        ] {
+            let error = ObjLoader::new()
+                .parse_with(source, "dir/scene.obj", &read_mtl)
+                .err()
+                .unwrap();
+            assert!(error.to_string().starts_with(message), "{error}");
+        }
+
+        // a missing library or an unknown material falls back to the default material
+        let loader = ObjLoader::new();
+        let source = "mtllib other.mtl\nusemtl white\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+        let model = loader
+            .parse_with(source, "dir/scene.obj", &read_mtl)
+            .unwrap();
+        assert_eq!(model.objects[0].material, "white");
+        let mut rec = HitRecordMat::new();
+        let ray = Ray::from_origin_dir(
+            &Point3::from_slice([0.2, 0.2, 1.0]),
+            &Vec3::from_slice([0.0, 0.0, -1.0]),
+        );
+        assert!(model
+            .hittable_list()
+            .hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+        assert!(Arc::ptr_eq(
+            rec.mat.as_ref().unwrap(),
+            loader.default_material.as_ref().unwrap()
+        ));
+
+        let error = parse_mtl("newmtl a\nKd 1 x 1\n", "scene.mtl").unwrap_err();
+        assert_eq!(error.to_string(), "scene.mtl:2: invalid number \"x\"");
+        let error = parse_mtl("Kd 1 1 1\n", "scene.mtl").unwrap_err();
+        assert_eq!(error.to_string(), "scene.mtl:1: Kd before newmtl");
+    }
+}
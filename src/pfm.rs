@@ -0,0 +1,157 @@
+use crate::color::Color;
+use crate::framebuffer::FrameBuffer;
+use crate::image_reader::{ImageReader, PnmParser};
+use crate::image_writer::ImageWriter;
+use std::error::Error;
+use std::io::{Read, Write};
+
+/// write Portable Float Map (*.pfm): 32-bit float RGB, little endian
+pub struct PfmWriter;
+
+impl ImageWriter for PfmWriter {
+    fn write(&self, framebuffer: &FrameBuffer, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let (width, height) = (framebuffer.width(), framebuffer.height());
+        // a negative scale means little endian
+        std::write!(out, "PF\n{width} {height}\n-1.0\n")?;
+
+        // scanlines are stored from bottom to top
+        let mut row = Vec::with_capacity(width.max(0) as usize * 12);
+        for j in (0..height).rev() {
+            row.clear();
+            for i in 0..width {
+                let color = framebuffer.pixel(i, j);
+                for c in [color.x(), color.y(), color.z()] {
+                    row.extend_from_slice(&(c as f32).to_le_bytes());
+                }
+            }
+            out.write_all(&row)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// read Portable Float Map (*.pfm): color (PF) or grayscale (Pf), either endianness
+pub struct PfmReader;
+
+impl ImageReader for PfmReader {
+    fn read(&self, input: &mut dyn Read) -> Result<FrameBuffer, Box<dyn Error>> {
+        let mut data = vec![];
+        input.read_to_end(&mut data)?;
+        let mut parser = PnmParser::new(&data);
+
+        let n_channels = match parser.next_token("magic number")? {
+            b"PF" => 3,
+            b"Pf" => 1,
+            magic => {
+                return Err(format!(
+                    "not a PFM image (magic number {:?})",
+                    String::from_utf8_lossy(magic)
+                )
+                .into())
+            }
+        };
+        let width = parser.next_u32("width")?;
+        let height = parser.next_u32("height")?;
+        let scale = parser.next_f64("scale")?;
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return Err(format!("invalid image size {width}x{height}").into());
+        }
+        if scale == 0.0 || !scale.is_finite() {
+            return Err(format!("invalid scale {scale}").into());
+        }
+
+        let bytes = parser.binary_data("scale")?;
+        let n_bytes = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|n| n.checked_mul(n_channels * 4))
+            .ok_or("image too large")?;
+        if bytes.len() < n_bytes {
+            return Err(format!(
+                "unexpected end of data: {} bytes of samples, expected {n_bytes}",
+                bytes.len()
+            )
+            .into());
+        }
+        let values = bytes[..n_bytes]
+            .chunks_exact(4)
+            .map(|b| {
+                let b = [b[0], b[1], b[2], b[3]];
+                // a negative scale means little endian
+                if scale < 0.0 {
+                    f32::from_le_bytes(b) as f64
+                } else {
+                    f32::from_be_bytes(b) as f64
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut framebuffer = FrameBuffer::new(width as i32, height as i32);
+        for (k, pixel) in values.chunks_exact(n_channels).enumerate() {
+            let (i, j) = (k % width as usize, k / width as usize);
+            let color = match pixel {
+                [r, g, b] => Color::from_slice([*r, *g, *b]),
+                _ => Color::from_slice([pixel[0]; 3]),
+            };
+            // scanlines are stored from bottom to top
+            framebuffer.set_pixel(i as i32, height as i32 - 1 - j as i32, &color);
+        }
+
+        Ok(framebuffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pfm_writer() {
+        let mut framebuffer = FrameBuffer::new(2, 2);
+        framebuffer.set_pixel(0, 0, &Color::from_slice([1.0, 2.0, 3.0]));
+        framebuffer.set_pixel(1, 1, &Color::from_slice([100.5, 0.25, -1.0]));
+
+        let mut out = vec![];
+        PfmWriter.write(&framebuffer, &mut out).unwrap();
+
+        let header = b"PF\n2 2\n-1.0\n";
+        assert_eq!(&out[..header.len()], header);
+        let values = out[header.len()..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [0.0, 0.0, 0.0, 100.5, 0.25, -1.0, 1.0, 2.0, 3.0, 0.0, 0.0, 0.0]
+        );
+
+        let decoded = PfmReader.read(&mut &out[..]).unwrap();
+        for (pixel, expected) in decoded.pixels().zip(framebuffer.pixels()) {
+            assert_eq!(pixel.e, expected.e);
+        }
+    }
+
+    #[test]
+    fn test_pfm_reader() {
+        // grayscale, big endian, 1x2
+        let mut data = b"Pf\n1 2\n1.0\n".to_vec();
+        data.extend_from_slice(&0.5f32.to_be_bytes());
+        data.extend_from_slice(&2.0f32.to_be_bytes());
+        let framebuffer = PfmReader.read(&mut &data[..]).unwrap();
+        assert_eq!(framebuffer.pixel(0, 0).e, [2.0, 2.0, 2.0]);
+        assert_eq!(framebuffer.pixel(0, 1).e, [0.5, 0.5, 0.5]);
+
+        for (data, message) in [
+            (&b"PF\n1 1\n0\n"[..], "invalid scale 0"),
+            (b"PX\n1 1\n-1\n", "not a PFM image"),
+            (b"PF\n1 1\n-1\n\x00\x00", "unexpected end of data: 2 bytes"),
+            (
+                b"PF\n2147483647 2147483647\n-1.0\n\x00\x00\x00\x00",
+                "image too large",
+            ),
+        ] {
+            let error = PfmReader.read(&mut &data[..]).unwrap_err();
+            assert!(error.to_string().starts_with(message), "{error}");
+        }
+    }
+}
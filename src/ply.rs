@@ -0,0 +1,532 @@
+use crate::color::Color;
+use crate::material::{LambertianVertexColor, Material};
+use crate::tone_map::srgb_to_linear;
+use crate::triangle_mesh::TriangleMesh;
+use crate::vec3::{Point3, Vec3};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::sync::Arc;
+
+/// read Stanford *.ply meshes: ASCII, binary little endian or binary big endian
+///
+/// uses the vertex properties x, y, z, nx, ny, nz, red, green, blue, u, v (or s, t)
+/// and the face lists vertex_indices (or vertex_index), polygons are triangulated as fans.
+/// other elements and properties are skipped.
+pub struct PlyLoader {
+    /// material of the mesh
+    pub mat: Option<Arc<dyn Material>>,
+}
+
+impl Default for PlyLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlyLoader {
+    /// Lambertian with the vertex colors as albedo (gray without vertex colors)
+    pub fn new() -> Self {
+        Self {
+            mat: Some(Arc::new(LambertianVertexColor::new(&Color::from_slice([
+                0.5, 0.5, 0.5,
+            ])))),
+        }
+    }
+
+    pub fn load(&self, file_name: &str) -> Result<TriangleMesh, Box<dyn Error>> {
+        let mut input =
+            BufReader::new(File::open(file_name).map_err(|e| format!("{file_name}: {e}"))?);
+        self.read(&mut input)
+            .map_err(|e| format!("{file_name}: {e}").into())
+    }
+
+    pub fn read(&self, input: &mut dyn Read) -> Result<TriangleMesh, Box<dyn Error>> {
+        let mut data = vec![];
+        input.read_to_end(&mut data)?;
+        let Header {
+            format,
+            elements,
+            length,
+        } = parse_header(&data)?;
+        let body = &data[length..];
+        let mut reader = BodyReader {
+            data: body,
+            pos: 0,
+            format,
+        };
+
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        let mut colors = vec![];
+        let mut has = (false, false, false);
+        let mut faces: Vec<Vec<i64>> = vec![];
+
+        for element in elements.iter() {
+            match element.name.as_str() {
+                "vertex" => {
+                    let find = |names: &[&str]| {
+                        element.properties.iter().position(|property| {
+                            property.list_count_type.is_none()
+                                && names.contains(&property.name.as_str())
+                        })
+                    };
+                    let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+                    let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+                    let uv = [
+                        find(&["u", "s", "texture_u", "texture_s"]),
+                        find(&["v", "t", "texture_v", "texture_t"]),
+                    ];
+                    let color = [
+                        find(&["red", "r", "diffuse_red"]),
+                        find(&["green", "g", "diffuse_green"]),
+                        find(&["blue", "b", "diffuse_blue"]),
+                    ];
+                    let [Some(x), Some(y), Some(z)] = position else {
+                        return Err("vertex element without x, y and z".into());
+                    };
+                    has = (
+                        normal.iter().all(Option::is_some),
+                        uv.iter().all(Option::is_some),
+                        color.iter().all(Option::is_some),
+                    );
+                    let capacity = element.count.min(body.len());
+                    positions.reserve(capacity);
+
+                    let mut values = vec![0.0; element.properties.len()];
+                    for k in 0..element.count {
+                        for (value, property) in values.iter_mut().zip(element.properties.iter()) {
+                            *value = reader
+                                .property(property)
+                                .map_err(|e| format!("vertex {k}: {e}"))?
+                                .first()
+                                .copied()
+                                .unwrap_or(0.0);
+                        }
+                        positions.push(Point3::from_slice([values[x], values[y], values[z]]));
+                        if let [Some(nx), Some(ny), Some(nz)] = normal {
+                            normals.push(Vec3::from_slice([values[nx], values[ny], values[nz]]));
+                        }
+                        if let [Some(u), Some(v)] = uv {
+                            uvs.push([values[u], values[v]]);
+                        }
+                        if let [Some(r), Some(g), Some(b)] = color {
+                            let [r, g, b] = [r, g, b].map(|c| {
+                                let property = &element.properties[c];
+                                match property.value_type.max_value() {
+                                    // integer colors are 8-bit (or 16-bit) sRGB
+                                    Some(max_value) => srgb_to_linear(values[c] / max_value),
+                                    None => values[c],
+                                }
+                            });
+                            colors.push(Color::from_slice([r, g, b]));
+                        }
+                    }
+                }
+                "face" => {
+                    let Some(indices) = element.properties.iter().position(|property| {
+                        property.list_count_type.is_some()
+                            && (property.name == "vertex_indices"
+                                || property.name == "vertex_index")
+                    }) else {
+                        return Err("face element without vertex_indices".into());
+                    };
+                    faces.reserve(element.count.min(body.len()));
+                    for k in 0..element.count {
+                        for (i, property) in element.properties.iter().enumerate() {
+                            let values = reader
+                                .property(property)
+                                .map_err(|e| format!("face {k}: {e}"))?;
+                            if i == indices {
+                                faces.push(values.iter().map(|&index| index as i64).collect());
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    for k in 0..element.count {
+                        for property in element.properties.iter() {
+                            reader
+                                .property(property)
+                                .map_err(|e| format!("{} {k}: {e}", element.name))?;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut indices = Vec::with_capacity(faces.len());
+        for (k, face) in faces.iter().enumerate() {
+            if face.len() < 3 {
+                return Err(format!("face {k} has {} vertices", face.len()).into());
+            }
+            if let Some(index) = face
+                .iter()
+                .find(|&&index| index < 0 || index as usize >= positions.len())
+            {
+                return Err(format!(
+                    "face {k}: vertex index {index} out of range ({} vertices)",
+                    positions.len()
+                )
+                .into());
+            }
+            for i in 1..face.len() - 1 {
+                indices.push([face[0] as u32, face[i] as u32, face[i + 1] as u32]);
+            }
+        }
+
+        let (has_normals, has_uvs, has_colors) = has;
+        Ok(TriangleMesh::new_with_attributes(
+            positions,
+            has_normals.then_some(normals),
+            has_uvs.then_some(uvs),
+            has_colors.then_some(colors),
+            indices,
+            self.mat.clone(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ValueType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ValueType {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "char" | "int8" => Ok(Self::Int8),
+            "uchar" | "uint8" => Ok(Self::UInt8),
+            "short" | "int16" => Ok(Self::Int16),
+            "ushort" | "uint16" => Ok(Self::UInt16),
+            "int" | "int32" => Ok(Self::Int32),
+            "uint" | "uint32" => Ok(Self::UInt32),
+            "float" | "float32" => Ok(Self::Float32),
+            "double" | "float64" => Ok(Self::Float64),
+            _ => Err(format!("unknown property type {name:?}")),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Float64 => 8,
+        }
+    }
+
+    /// maximum of unsigned integer types (used to normalize colors)
+    fn max_value(&self) -> Option<f64> {
+        match self {
+            Self::UInt8 => Some(255.0),
+            Self::UInt16 => Some(65535.0),
+            _ => None,
+        }
+    }
+}
+
+struct Property {
+    name: String,
+    value_type: ValueType,
+    /// type of the number of items if the property is a list
+    list_count_type: Option<ValueType>,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    /// size in bytes including end_header
+    length: usize,
+}
+
+fn parse_header(data: &[u8]) -> Result<Header, Box<dyn Error>> {
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    let mut pos = 0;
+    let mut line_number = 0;
+
+    loop {
+        let Some(length) = data[pos..].iter().position(|&b| b == b'\n') else {
+            return Err("missing end_header".into());
+        };
+        let line = String::from_utf8_lossy(&data[pos..pos + length]).into_owned();
+        pos += length + 1;
+        line_number += 1;
+        let error = |message: String| format!("header line {line_number}: {message}");
+
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        if line_number == 1 {
+            if tokens != ["ply"] {
+                return Err("not a PLY file".into());
+            }
+            continue;
+        }
+        match tokens[..] {
+            ["format", name, _version] => {
+                format = Some(match name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(error(format!("unknown format {name:?}")).into()),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| error(format!("invalid element count {count:?}")))?,
+                properties: vec![],
+            }),
+            ["property", "list", count_type, value_type, name] => {
+                let Some(element) = elements.last_mut() else {
+                    return Err(error("property before element".to_string()).into());
+                };
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    value_type: ValueType::parse(value_type).map_err(error)?,
+                    list_count_type: Some(ValueType::parse(count_type).map_err(error)?),
+                });
+            }
+            ["property", value_type, name] => {
+                let Some(element) = elements.last_mut() else {
+                    return Err(error("property before element".to_string()).into());
+                };
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    value_type: ValueType::parse(value_type).map_err(error)?,
+                    list_count_type: None,
+                });
+            }
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(error(format!("invalid header line {line:?}")).into()),
+        }
+    }
+
+    let Some(format) = format else {
+        return Err("missing format".into());
+    };
+
+    Ok(Header {
+        format,
+        elements,
+        length: pos,
+    })
+}
+
+/// values of the elements after the header
+struct BodyReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    format: Format,
+}
+
+impl BodyReader<'_> {
+    /// the value of a scalar property or the items of a list property
+    fn property(&mut self, property: &Property) -> Result<Vec<f64>, String> {
+        match property.list_count_type {
+            Some(count_type) => {
+                let count = self.value(count_type)?;
+                if count < 0.0 || count.fract() != 0.0 {
+                    return Err(format!("invalid list size {count}"));
+                }
+                (0..count as usize)
+                    .map(|_| self.value(property.value_type))
+                    .collect()
+            }
+            None => Ok(vec![self.value(property.value_type)?]),
+        }
+    }
+
+    fn value(&mut self, value_type: ValueType) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            let start = self.pos
+                + self.data[self.pos..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_whitespace())
+                    .count();
+            let end = start
+                + self.data[start..]
+                    .iter()
+                    .take_while(|b| !b.is_ascii_whitespace())
+                    .count();
+            self.pos = end;
+            let token = std::str::from_utf8(&self.data[start..end]).unwrap_or("");
+            if token.is_empty() {
+                return Err("unexpected end of data".to_string());
+            }
+            return token
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number {token:?}"));
+        }
+
+        let size = value_type.size();
+        if self.pos + size > self.data.len() {
+            return Err("unexpected end of data".to_string());
+        }
+        let mut bytes = [0; 8];
+        bytes[..size].copy_from_slice(&self.data[self.pos..self.pos + size]);
+        self.pos += size;
+        if self.format == Format::BinaryBigEndian {
+            bytes[..size].reverse();
+        }
+
+        let value = match value_type {
+            ValueType::Int8 => bytes[0] as i8 as f64,
+            ValueType::UInt8 => bytes[0] as f64,
+            ValueType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ValueType::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ValueType::Int32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ValueType::UInt32 => {
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+            }
+            ValueType::Float32 => {
+                f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+            }
+            ValueType::Float64 => f64::from_le_bytes(bytes),
+        };
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable_material::{HitRecordMat, HittableAaBb};
+    use crate::interval::consts;
+    use crate::ray::Ray;
+
+    /// a unit square in z = 0: one quad face with another property after the list, red at x = 0
+    const ASCII: &str = "ply
+format ascii 1.0
+comment made by hand
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+property uchar flags
+end_header
+0 0 0 255 0 0
+1 0 0 255 0 0
+1 1 0 0 0 255
+0 1 0 0 0 255
+4 0 1 2 3 7
+";
+
+    /// the same mesh in a binary encoding
+    fn binary(little_endian: bool) -> Vec<u8> {
+        let format = if little_endian {
+            "binary_little_endian"
+        } else {
+            "binary_big_endian"
+        };
+        let mut data = ASCII
+            .replace("format ascii", &format!("format {format}"))
+            .split("end_header\n")
+            .next()
+            .unwrap()
+            .to_string()
+            .into_bytes();
+        data.extend_from_slice(b"end_header\n");
+        for (x, y, red) in [
+            (0.0f32, 0.0f32, 255u8),
+            (1.0, 0.0, 255),
+            (1.0, 1.0, 0),
+            (0.0, 1.0, 0),
+        ] {
+            for value in [x, y, 0.0] {
+                data.extend_from_slice(&if little_endian {
+                    value.to_le_bytes()
+                } else {
+                    value.to_be_bytes()
+                });
+            }
+            data.extend_from_slice(&[red, 0, 255 - red]);
+        }
+        data.push(4);
+        for index in [0i32, 1, 2, 3] {
+            data.extend_from_slice(&if little_endian {
+                index.to_le_bytes()
+            } else {
+                index.to_be_bytes()
+            });
+        }
+        data.push(7);
+        data
+    }
+
+    #[test]
+    fn test_ply_reader() {
+        for data in [ASCII.as_bytes().to_vec(), binary(true), binary(false)] {
+            let mesh = PlyLoader::new().read(&mut &data[..]).unwrap();
+            assert_eq!(mesh.n_vertices(), 4);
+            assert_eq!(mesh.n_triangles(), 2);
+
+            // halfway between the red and the blue vertices
+            let mut rec = HitRecordMat::new();
+            let ray = Ray::from_origin_dir(
+                &Point3::from_slice([0.75, 0.5, 1.0]),
+                &Vec3::from_slice([0.0, 0.0, -1.0]),
+            );
+            assert!(mesh.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+            let color = rec.color.unwrap();
+            assert!((color.x() - 0.5).abs() < 1e-9 && (color.z() - 0.5).abs() < 1e-9);
+            assert_eq!(color.y(), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_ply_errors() {
+        for (data, message) in [
+            ("obj\n", "not a PLY file"),
+            ("ply\nformat ascii 1.0\nelement vertex 1\n", "missing end_header"),
+            (
+                "ply\nformat ascii 1.0\nelement vertex 1\nproperty real x\nend_header\n",
+                "header line 4: unknown property type \"real\"",
+            ),
+            (
+                "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\n0\n",
+                "vertex element without x, y and z",
+            ),
+            (
+                "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0\n",
+                "vertex 0: unexpected end of data",
+            ),
+            (
+                "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_index\nend_header\n0 0 0\n3 0 0 1\n",
+                "face 0: vertex index 1 out of range (1 vertices)",
+            ),
+        ] {
+            let error = PlyLoader::new().read(&mut data.as_bytes()).err().unwrap();
+            assert!(error.to_string().starts_with(message), "{error}");
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::framebuffer::FrameBuffer;
+use crate::image_writer::ImageWriter;
+use crate::tone_map::{ToneMap, Transfer};
+use crate::zlib;
+use std::error::Error;
+use std::io::Write;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+const CRC_TABLE: [u32; 256] = crc_table();
+
+/// write *.png image: RGB, 8-bit or 16-bit
+pub struct PngWriter {
+    /// conversion of the HDR colors to samples
+    pub tone_map: ToneMap,
+    /// bits per sample: 8 or 16
+    pub bit_depth: u8,
+}
+
+impl Default for PngWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PngWriter {
+    /// 8-bit, colors as they are
+    pub fn new() -> Self {
+        Self {
+            tone_map: ToneMap::new(),
+            bit_depth: 8,
+        }
+    }
+
+    /// 8-bit with gamma correction
+    pub fn new_gamma(gamma: f64) -> Self {
+        Self {
+            tone_map: ToneMap::new_gamma(gamma),
+            ..Self::new()
+        }
+    }
+
+    /// scanlines, each preceded by its filter type byte
+    fn filtered_scanlines(&self, framebuffer: &FrameBuffer) -> Vec<u8> {
+        let max_value = if self.bit_depth == 16 { 65535 } else { 255 };
+        let bytes_per_pixel = 3 * self.bit_depth as usize / 8;
+        let row_size = framebuffer.width() as usize * bytes_per_pixel;
+
+        let mut data = Vec::with_capacity((row_size + 1) * framebuffer.height() as usize);
+        let mut prior = vec![0; row_size];
+        let mut row = Vec::with_capacity(row_size);
+        for j in 0..framebuffer.height() {
+            row.clear();
+            for i in 0..framebuffer.width() {
+                let color = framebuffer.pixel(i, j);
+                for sample in self.tone_map.encode(&color, max_value) {
+                    if self.bit_depth == 16 {
+                        row.extend_from_slice(&sample.to_be_bytes());
+                    } else {
+                        row.push(sample as u8);
+                    }
+                }
+            }
+
+            let (filter_type, filtered) = filter_row(&row, &prior, bytes_per_pixel);
+            data.push(filter_type);
+            data.extend_from_slice(&filtered);
+            std::mem::swap(&mut prior, &mut row);
+        }
+
+        data
+    }
+}
+
+impl ImageWriter for PngWriter {
+    fn write(&self, framebuffer: &FrameBuffer, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        if self.bit_depth != 8 && self.bit_depth != 16 {
+            return Err(format!("unsupported PNG bit depth {}", self.bit_depth).into());
+        }
+        let (width, height) = (framebuffer.width(), framebuffer.height());
+        if width <= 0 || height <= 0 {
+            return Err(format!("invalid PNG image size {width}x{height}").into());
+        }
+
+        out.write_all(&SIGNATURE)?;
+
+        let mut header = vec![];
+        header.extend_from_slice(&(width as u32).to_be_bytes());
+        header.extend_from_slice(&(height as u32).to_be_bytes());
+        // bit depth, color type 2 (RGB), compression 0, filter 0, no interlace
+        header.extend_from_slice(&[self.bit_depth, 2, 0, 0, 0]);
+        write_chunk(out, b"IHDR", &header)?;
+        if self.tone_map.transfer == Transfer::Srgb {
+            // rendering intent 0: perceptual
+            write_chunk(out, b"sRGB", &[0])?;
+        }
+
+        let data = zlib::compress(&self.filtered_scanlines(framebuffer));
+        write_chunk(out, b"IDAT", &data)?;
+        write_chunk(out, b"IEND", &[])?;
+
+        Ok(())
+    }
+}
+
+/// length, type, data and CRC of the type and data
+fn write_chunk(
+    out: &mut dyn Write,
+    chunk_type: &[u8; 4],
+    data: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(chunk_type)?;
+    out.write_all(data)?;
+    let crc = crc32_update(crc32_update(0xffffffff, chunk_type), data) ^ 0xffffffff;
+    out.write_all(&crc.to_be_bytes())?;
+
+    Ok(())
+}
+
+/// choose the filter with the minimum sum of absolute differences (as recommended by the spec)
+fn filter_row(row: &[u8], prior: &[u8], bytes_per_pixel: usize) -> (u8, Vec<u8>) {
+    let left = |k: usize| {
+        if k >= bytes_per_pixel {
+            row[k - bytes_per_pixel]
+        } else {
+            0
+        }
+    };
+    let upper_left = |k: usize| {
+        if k >= bytes_per_pixel {
+            prior[k - bytes_per_pixel]
+        } else {
+            0
+        }
+    };
+
+    let mut best: Option<(u64, u8, Vec<u8>)> = None;
+    for filter_type in 0..5u8 {
+        let filtered = (0..row.len())
+            .map(|k| {
+                let predictor = match filter_type {
+                    0 => 0,
+                    1 => left(k),
+                    2 => prior[k],
+                    3 => ((left(k) as u16 + prior[k] as u16) / 2) as u8,
+                    _ => paeth(left(k), prior[k], upper_left(k)),
+                };
+                row[k].wrapping_sub(predictor)
+            })
+            .collect::<Vec<_>>();
+        let cost = filtered
+            .iter()
+            .map(|&b| (b as i8).unsigned_abs() as u64)
+            .sum::<u64>();
+        if best
+            .as_ref()
+            .is_none_or(|(best_cost, _, _)| cost < *best_cost)
+        {
+            best = Some((cost, filter_type, filtered));
+        }
+    }
+    let (_, filter_type, filtered) = best.unwrap();
+
+    (filter_type, filtered)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+
+    table
+}
+
+/// update a running CRC-32 (initial value 0xffffffff, final xor 0xffffffff)
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(
+            crc32_update(0xffffffff, b"123456789") ^ 0xffffffff,
+            0xcbf43926
+        );
+        // CRC of the IEND chunk found in every PNG file
+        assert_eq!(crc32_update(0xffffffff, b"IEND") ^ 0xffffffff, 0xae426082);
+    }
+
+    #[test]
+    fn test_png_writer() {
+        let mut framebuffer = FrameBuffer::new(4, 3);
+        for j in 0..3 {
+            for i in 0..4 {
+                framebuffer.set_pixel(
+                    i,
+                    j,
+                    &Color::from_slice([i as f64 / 4.0, j as f64 / 3.0, 0.5]),
+                );
+            }
+        }
+
+        for bit_depth in [8, 16] {
+            let mut writer = PngWriter::new_gamma(0.5);
+            writer.bit_depth = bit_depth;
+            let mut out = vec![];
+            writer.write(&framebuffer, &mut out).unwrap();
+
+            assert_eq!(out[..8], SIGNATURE);
+            assert_eq!(&out[12..16], b"IHDR");
+            assert_eq!(out[16..24], [0, 0, 0, 4, 0, 0, 0, 3]);
+            assert_eq!(out[24], bit_depth);
+            assert_eq!(
+                out[out.len() - 12..],
+                [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+            );
+        }
+
+        let mut writer = PngWriter::new();
+        writer.bit_depth = 12;
+        assert!(writer.write(&framebuffer, &mut vec![]).is_err());
+    }
+}
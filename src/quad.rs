@@ -0,0 +1,146 @@
+use crate::aabb::AaBb;
+use crate::hittable_material::{HitRecordMat, HittableAaBb, HittableMat};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+/// parallelogram spanned by the edges `u` and `v` from the corner `q`
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    /// n / (n . n) with the plane normal n = u x v, maps a point in the plane to (alpha, beta)
+    w: Vec3,
+    mat: Option<Arc<dyn Material>>,
+    hbox: AaBb,
+    normal: Vec3,
+    /// plane equation: normal . p = d
+    d: f64,
+}
+
+impl Quad {
+    pub fn new(q: &Point3, u: &Vec3, v: &Vec3, mat: Option<Arc<dyn Material>>) -> Self {
+        let n = u.cross(v);
+        let normal = Vec3::new_unit_vec(n.clone());
+        let w = n.clone() / n.dot(&n);
+
+        // the box of the four corners, padded because a quad parallel to an axis is flat
+        let box_diagonal_1 = AaBb::new_by_two_points(q, &(q.clone() + u.clone() + v.clone()));
+        let box_diagonal_2 =
+            AaBb::new_by_two_points(&(q.clone() + u.clone()), &(q.clone() + v.clone()));
+        let hbox = AaBb::new_by_two_aabb(&box_diagonal_1, &box_diagonal_2).pad_to_minimums(0.0001);
+
+        Self {
+            q: q.clone(),
+            u: u.clone(),
+            v: v.clone(),
+            w,
+            mat,
+            hbox,
+            d: normal.dot(q),
+            normal,
+        }
+    }
+}
+
+impl HittableAaBb for Quad {
+    fn hit_aabb(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        let denom = self.normal.dot(ray.direction());
+
+        // no hit if the ray is parallel to the plane
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = (self.d - self.normal.dot(ray.origin())) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        // planar coordinates of the hit point in the basis u, v
+        let intersection = ray.at(t);
+        let planar_hitpt_vector = intersection.clone() - self.q.clone();
+        let alpha = self.w.dot(&planar_hitpt_vector.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&planar_hitpt_vector));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = intersection;
+        rec.u = alpha;
+        rec.v = beta;
+        rec.barycentric = [0.0; 3];
+        rec.color = None;
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(ray, &self.normal);
+
+        true
+    }
+
+    fn bounding_box(&self) -> AaBb {
+        self.hbox.clone()
+    }
+}
+
+impl HittableMat for Quad {
+    fn hit_mat(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        self.hit_aabb(ray, ray_t, rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::consts;
+
+    #[test]
+    fn test_quad_hit() {
+        // unit square in the plane z = -1, facing +z
+        let quad = Quad::new(
+            &Point3::from_slice([-0.5, -0.5, -1.0]),
+            &Vec3::from_slice([1.0, 0.0, 0.0]),
+            &Vec3::from_slice([0.0, 1.0, 0.0]),
+            None,
+        );
+        let bbox = quad.bounding_box();
+        assert!(bbox.z.size() > 0.0);
+        assert_eq!((bbox.x.min, bbox.x.max), (-0.5, 0.5));
+
+        let mut rec = HitRecordMat::new();
+        let ray = Ray::from_origin_dir(
+            &Point3::from_slice([0.25, -0.25, 0.0]),
+            &Vec3::from_slice([0.0, 0.0, -2.0]),
+        );
+        assert!(quad.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+        assert_eq!(rec.t, 0.5);
+        assert_eq!(rec.p.e, [0.25, -0.25, -1.0]);
+        assert_eq!((rec.u, rec.v), (0.75, 0.25));
+        assert_eq!(rec.normal.e, [0.0, 0.0, 1.0]);
+        assert!(rec.front_face);
+
+        // from behind
+        let ray = Ray::from_origin_dir(
+            &Point3::from_slice([0.0, 0.0, -2.0]),
+            &Vec3::from_slice([0.0, 0.0, 1.0]),
+        );
+        assert!(quad.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+        assert_eq!(rec.normal.e, [0.0, 0.0, -1.0]);
+        assert!(!rec.front_face);
+
+        for (origin, direction) in [
+            // outside of the edges
+            ([0.6, 0.0, 0.0], [0.0, 0.0, -1.0]),
+            // parallel
+            ([0.0, 0.0, -1.0], [1.0, 0.0, 0.0]),
+            // pointing away
+            ([0.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ] {
+            let ray =
+                Ray::from_origin_dir(&Point3::from_slice(origin), &Vec3::from_slice(direction));
+            assert!(!quad.hit_aabb(&ray, Interval::new_by_value(0.0, f64::INFINITY), &mut rec));
+        }
+    }
+}
@@ -6,6 +6,12 @@ pub struct Ray {
     tm: f64,
 }
 
+impl Default for Ray {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Ray {
     pub fn new() -> Self {
         Self {
@@ -61,7 +67,7 @@ mod tests {
         let ans_2 = Point3::from_slice([2.0, 2.0, 2.0]);
         let ans_10 = Point3::from_slice([10.0, 10.0, 10.0]);
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!((ans_2 - ray.at(2.0)).norm_squared() < epsilon);
         assert!((ans_10 - ray.at(10.0)).norm_squared() < epsilon);
     }
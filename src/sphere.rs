@@ -46,7 +46,7 @@ impl Hittable for Sphere {
         rec.t = root;
         rec.p = ray.at(rec.t);
         let outward_normal = (rec.p.clone() - self.center.clone()) / self.radius;
-        rec.set_face_normal(&ray, &outward_normal);
+        rec.set_face_normal(ray, &outward_normal);
 
         true
     }
@@ -82,7 +82,7 @@ impl HittableV2 for Sphere {
         rec.t = root;
         rec.p = ray.at(rec.t);
         let outward_normal = (rec.p.clone() - self.center.clone()) / self.radius;
-        rec.set_face_normal(&ray, &outward_normal);
+        rec.set_face_normal(ray, &outward_normal);
 
         true
     }
@@ -1,28 +1,23 @@
 use crate::aabb::AaBb;
-use crate::hittable_material::{HitRecordMat, HittableAaBb, HittableMat};
+use crate::hittable_material::{HitRecordMat, HittableAaBb};
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::{Point3, Vec3};
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::Arc;
 
 /// sphere with AaBb
 pub struct SphereAaBb {
     center_1: Point3,
     radius: f64,
-    mat: Option<Rc<RefCell<dyn Material>>>,
+    mat: Option<Arc<dyn Material>>,
     is_moving: bool,
     center_vec: Vec3,
     hbox: AaBb,
 }
 
 impl SphereAaBb {
-    pub fn new_stationary(
-        center: &Point3,
-        radius: f64,
-        mat: Option<Rc<RefCell<dyn Material>>>,
-    ) -> Self {
+    pub fn new_stationary(center: &Point3, radius: f64, mat: Option<Arc<dyn Material>>) -> Self {
         let mut sphere = Self {
             center_1: center.clone(),
             radius: radius.max(0.0),
@@ -43,7 +38,7 @@ impl SphereAaBb {
         center_1: &Point3,
         center_2: &Point3,
         radius: f64,
-        mat: Option<Rc<RefCell<dyn Material>>>,
+        mat: Option<Arc<dyn Material>>,
     ) -> Self {
         let mut sphere = Self {
             center_1: center_1.clone(),
@@ -104,7 +99,10 @@ impl HittableAaBb for SphereAaBb {
         rec.t = root;
         rec.p = ray.at(rec.t);
         let outward_normal = (rec.p.clone() - center.clone()) / self.radius;
-        rec.set_face_normal(&ray, &outward_normal);
+        rec.set_face_normal(ray, &outward_normal);
+        rec.set_sphere_uv(&outward_normal);
+        rec.barycentric = [0.0; 3];
+        rec.color = None;
         rec.mat = self.mat.clone();
 
         true
@@ -3,17 +3,16 @@ use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::Point3;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct SphereMat {
     center: Point3,
     radius: f64,
-    mat: Option<Rc<RefCell<dyn Material>>>,
+    mat: Option<Arc<dyn Material>>,
 }
 
 impl SphereMat {
-    pub fn new(center: &Point3, radius: f64, mat: Option<Rc<RefCell<dyn Material>>>) -> Self {
+    pub fn new(center: &Point3, radius: f64, mat: Option<Arc<dyn Material>>) -> Self {
         Self {
             center: center.clone(),
             radius: radius.max(0.0),
@@ -47,7 +46,10 @@ impl HittableMat for SphereMat {
         rec.t = root;
         rec.p = ray.at(rec.t);
         let outward_normal = (rec.p.clone() - self.center.clone()) / self.radius;
-        rec.set_face_normal(&ray, &outward_normal);
+        rec.set_face_normal(ray, &outward_normal);
+        rec.set_sphere_uv(&outward_normal);
+        rec.barycentric = [0.0; 3];
+        rec.color = None;
         rec.mat = self.mat.clone();
 
         true
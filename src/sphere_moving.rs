@@ -3,23 +3,18 @@ use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::{Point3, Vec3};
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct SphereMoving {
     center_1: Point3,
     radius: f64,
-    mat: Option<Rc<RefCell<dyn Material>>>,
+    mat: Option<Arc<dyn Material>>,
     is_moving: bool,
     center_vec: Vec3,
 }
 
 impl SphereMoving {
-    pub fn new_stationary(
-        center: &Point3,
-        radius: f64,
-        mat: Option<Rc<RefCell<dyn Material>>>,
-    ) -> Self {
+    pub fn new_stationary(center: &Point3, radius: f64, mat: Option<Arc<dyn Material>>) -> Self {
         Self {
             center_1: center.clone(),
             radius: radius.max(0.0),
@@ -33,7 +28,7 @@ impl SphereMoving {
         center_1: &Point3,
         center_2: &Point3,
         radius: f64,
-        mat: Option<Rc<RefCell<dyn Material>>>,
+        mat: Option<Arc<dyn Material>>,
     ) -> Self {
         Self {
             center_1: center_1.clone(),
@@ -80,7 +75,10 @@ impl HittableMat for SphereMoving {
         rec.t = root;
         rec.p = ray.at(rec.t);
         let outward_normal = (rec.p.clone() - center.clone()) / self.radius;
-        rec.set_face_normal(&ray, &outward_normal);
+        rec.set_face_normal(ray, &outward_normal);
+        rec.set_sphere_uv(&outward_normal);
+        rec.barycentric = [0.0; 3];
+        rec.color = None;
         rec.mat = self.mat.clone();
 
         true
@@ -1,12 +1,14 @@
 use ray_tracing_rust::camera::Camera;
 use ray_tracing_rust::color::Color;
 use ray_tracing_rust::hittable_list::HittableList;
+use ray_tracing_rust::image_writer::save_image;
+use ray_tracing_rust::integrator::MaterialIntegrator;
 use ray_tracing_rust::material::{Dielectric, Lambertian, Material, MetalFuzz};
 use ray_tracing_rust::sphere_material::SphereMat;
+use ray_tracing_rust::tone_map::ToneMap;
 use ray_tracing_rust::vec3::Point3;
-use std::cell::RefCell;
 use std::error::Error;
-use std::rc::Rc;
+use std::sync::Arc;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let argv = std::env::args().collect::<Vec<String>>();
@@ -17,38 +19,40 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let mut world = HittableList::new();
 
-    let material_ground: Option<Rc<RefCell<dyn Material>>> = Some(Rc::new(RefCell::new(
-        Lambertian::new(&Color::from_slice([0.8, 0.8, 0.0])),
-    )));
-    let material_center: Option<Rc<RefCell<dyn Material>>> = Some(Rc::new(RefCell::new(
-        Lambertian::new(&Color::from_slice([0.1, 0.2, 0.5])),
-    )));
-    let material_left: Option<Rc<RefCell<dyn Material>>> =
-        Some(Rc::new(RefCell::new(Dielectric::new(1.50))));
-    let material_right: Option<Rc<RefCell<dyn Material>>> = Some(Rc::new(RefCell::new(
-        MetalFuzz::new(&Color::from_slice([0.8, 0.6, 0.2]), 1.0),
+    let material_ground: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::from_slice([
+            0.8, 0.8, 0.0,
+        ]))));
+    let material_center: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::from_slice([
+            0.1, 0.2, 0.5,
+        ]))));
+    let material_left: Option<Arc<dyn Material>> = Some(Arc::new(Dielectric::new(1.50)));
+    let material_right: Option<Arc<dyn Material>> = Some(Arc::new(MetalFuzz::new(
+        &Color::from_slice([0.8, 0.6, 0.2]),
+        1.0,
     )));
 
-    world.add(Rc::new(RefCell::new(SphereMat::new(
+    world.add(Arc::new(SphereMat::new(
         &Point3::from_slice([0.0, -100.5, -1.0]),
         100.0,
         material_ground,
-    ))));
-    world.add(Rc::new(RefCell::new(SphereMat::new(
+    )));
+    world.add(Arc::new(SphereMat::new(
         &Point3::from_slice([0.0, 0.0, -1.2]),
         0.5,
         material_center,
-    ))));
-    world.add(Rc::new(RefCell::new(SphereMat::new(
+    )));
+    world.add(Arc::new(SphereMat::new(
         &Point3::from_slice([-1.0, 0.0, -1.0]),
         0.5,
         material_left,
-    ))));
-    world.add(Rc::new(RefCell::new(SphereMat::new(
+    )));
+    world.add(Arc::new(SphereMat::new(
         &Point3::from_slice([1.0, 0.0, -1.0]),
         0.5,
         material_right,
-    ))));
+    )));
 
     let mut camera = Camera::new();
     camera.aspect_ratio = 16.0 / 9.0;
@@ -57,7 +61,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     camera.max_depth = 50;
 
     let gamma = 0.50;
-    camera.render_material(gamma, &world, &file_name)?;
+    let framebuffer = camera.render(&world, &MaterialIntegrator);
+    save_image(&framebuffer, &file_name, &ToneMap::new_gamma(gamma))?;
 
     Ok(())
 }
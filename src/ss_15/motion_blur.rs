@@ -1,13 +1,17 @@
+use ray_tracing_rust::bvh_linear::LinearBvh;
 use ray_tracing_rust::camera::Camera;
 use ray_tracing_rust::color::Color;
-use ray_tracing_rust::hittable_list::HittableList;
+use ray_tracing_rust::hittable_list_aabb::HittableListAaBb;
+use ray_tracing_rust::hittable_material::HittableAaBb;
+use ray_tracing_rust::image_writer::save_image;
+use ray_tracing_rust::integrator::MaterialIntegrator;
 use ray_tracing_rust::material::{DielectricV3, Lambertian, Material, MetalFuzz};
-use ray_tracing_rust::sphere_moving::SphereMoving;
+use ray_tracing_rust::sphere_aabb::SphereAaBb;
+use ray_tracing_rust::tone_map::ToneMap;
 use ray_tracing_rust::utl;
 use ray_tracing_rust::vec3::{Point3, Vec3};
-use std::cell::RefCell;
 use std::error::Error;
-use std::rc::Rc;
+use std::sync::Arc;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let argv = std::env::args().collect::<Vec<String>>();
@@ -16,17 +20,18 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
     let file_name = argv[1].clone();
 
-    let mut world = HittableList::new();
-    let material_ground: Option<Rc<RefCell<dyn Material>>> = Some(Rc::new(RefCell::new(
-        Lambertian::new(&Color::from_slice([0.5, 0.5, 0.5])),
-    )));
-    world.add(Rc::new(RefCell::new(SphereMoving::new_stationary(
+    let mut world: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+    let material_ground: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::from_slice([
+            0.5, 0.5, 0.5,
+        ]))));
+    world.add(Arc::new(SphereAaBb::new_stationary(
         &Point3::from_slice([0.0, -100.0, 0.0]),
         100.0,
         material_ground,
-    ))));
+    )));
 
-    let mut random = utl::Random::new();
+    let mut random = utl::Random::new_by_seed(0);
     for a in -11..11 {
         for b in -11..11 {
             let choose_mat = random.random_f64();
@@ -37,70 +42,73 @@ fn main() -> Result<(), Box<dyn Error>> {
             ]);
 
             if (center.clone() - Point3::from_slice([4.0, 0.2, 0.0])).norm() > 0.9 {
-                let mut material_sphere: Option<Rc<RefCell<dyn Material>>> = None;
+                let material_sphere: Option<Arc<dyn Material>>;
 
                 if choose_mat < 0.8 {
                     // diffuse
-                    let albedo = Color::random() * Color::random();
-                    material_sphere = Some(Rc::new(RefCell::new(Lambertian::new(&albedo))));
+                    let albedo = Color::random(&mut random) * Color::random(&mut random);
+                    material_sphere = Some(Arc::new(Lambertian::new(&albedo)));
                     let center_2 = center.clone()
                         + Vec3::from_slice([0.0, random.random_f64_range(0.0, 0.5), 0.0]);
-                    world.add(Rc::new(RefCell::new(SphereMoving::new_moving(
+                    world.add(Arc::new(SphereAaBb::new_moving(
                         &center,
                         &center_2,
                         0.2,
                         material_sphere,
-                    ))));
+                    )));
                 } else if choose_mat < 0.95 {
                     // metal
-                    let albedo = Color::random_by_range(0.5, 1.0);
+                    let albedo = Color::random_by_range(0.5, 1.0, &mut random);
                     let fuzz = random.random_f64_range(0.0, 0.5);
-                    material_sphere = Some(Rc::new(RefCell::new(MetalFuzz::new(&albedo, fuzz))));
+                    material_sphere = Some(Arc::new(MetalFuzz::new(&albedo, fuzz)));
 
-                    world.add(Rc::new(RefCell::new(SphereMoving::new_stationary(
+                    world.add(Arc::new(SphereAaBb::new_stationary(
                         &center,
                         0.2,
                         material_sphere,
-                    ))));
+                    )));
                 } else {
                     // glass
-                    material_sphere = Some(Rc::new(RefCell::new(DielectricV3::new(1.5))));
+                    material_sphere = Some(Arc::new(DielectricV3::new(1.5)));
 
-                    world.add(Rc::new(RefCell::new(SphereMoving::new_stationary(
+                    world.add(Arc::new(SphereAaBb::new_stationary(
                         &center,
                         0.2,
                         material_sphere,
-                    ))));
+                    )));
                 }
             }
         }
     }
 
-    let material_1: Option<Rc<RefCell<dyn Material>>> =
-        Some(Rc::new(RefCell::new(DielectricV3::new(1.5))));
-    world.add(Rc::new(RefCell::new(SphereMoving::new_stationary(
+    let material_1: Option<Arc<dyn Material>> = Some(Arc::new(DielectricV3::new(1.5)));
+    world.add(Arc::new(SphereAaBb::new_stationary(
         &Point3::from_slice([0.0, 1.0, 0.0]),
         1.0,
         material_1,
-    ))));
-
-    let material_2: Option<Rc<RefCell<dyn Material>>> = Some(Rc::new(RefCell::new(
-        Lambertian::new(&Color::from_slice([0.4, 0.2, 0.1])),
     )));
-    world.add(Rc::new(RefCell::new(SphereMoving::new_stationary(
+
+    let material_2: Option<Arc<dyn Material>> =
+        Some(Arc::new(Lambertian::new(&Color::from_slice([
+            0.4, 0.2, 0.1,
+        ]))));
+    world.add(Arc::new(SphereAaBb::new_stationary(
         &Point3::from_slice([-4.0, 1.0, 0.0]),
         1.0,
         material_2,
-    ))));
+    )));
 
-    let material_3: Option<Rc<RefCell<dyn Material>>> = Some(Rc::new(RefCell::new(
-        MetalFuzz::new(&Color::from_slice([0.7, 0.6, 0.5]), 0.0),
+    let material_3: Option<Arc<dyn Material>> = Some(Arc::new(MetalFuzz::new(
+        &Color::from_slice([0.7, 0.6, 0.5]),
+        0.0,
     )));
-    world.add(Rc::new(RefCell::new(SphereMoving::new_stationary(
+    world.add(Arc::new(SphereAaBb::new_stationary(
         &Point3::from_slice([4.0, 1.0, 0.0]),
         1.0,
         material_3,
-    ))));
+    )));
+
+    let world = LinearBvh::new(world);
 
     let mut camera = Camera::new();
     camera.aspect_ratio = 16.0 / 9.0;
@@ -117,7 +125,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     camera.focus_dist = 10.0;
 
     let gamma = 0.50;
-    camera.render_motion_blur(gamma, &world, &file_name)?;
+    let framebuffer = camera.render(&world, &MaterialIntegrator);
+    save_image(&framebuffer, &file_name, &ToneMap::new_gamma(gamma))?;
 
     Ok(())
 }
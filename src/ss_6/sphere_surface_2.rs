@@ -4,15 +4,14 @@ use ray_tracing_rust::sphere::Sphere;
 use ray_tracing_rust::utl::constans;
 use ray_tracing_rust::vec3::{Point3, Vec3};
 use ray_tracing_rust::{hittable::HitRecord, hittable::Hittable, hittable_list::HittableList};
-use std::cell::RefCell;
 use std::error::Error;
 use std::fs::File;
 use std::io::Write;
-use std::rc::Rc;
+use std::sync::Arc;
 
 fn ray_color<T: Hittable>(ray: &Ray, world: &T) -> Color {
     let mut rec = HitRecord::new();
-    if world.hit(&ray, 0.0, constans::INFINITY, &mut rec) {
+    if world.hit(ray, 0.0, constans::INFINITY, &mut rec) {
         return 0.5 * (rec.normal.clone() + Color::from_slice([1.0, 1.0, 1.0]));
     }
 
@@ -39,14 +38,14 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // world
     let mut world = HittableList::new();
-    world.add(Rc::new(RefCell::new(Sphere::new(
+    world.add(Arc::new(Sphere::new(
         &Point3::from_slice([0.0, 0.0, -1.0]),
         0.5,
-    ))));
-    world.add(Rc::new(RefCell::new(Sphere::new(
+    )));
+    world.add(Arc::new(Sphere::new(
         &Point3::from_slice([0.0, -100.5, -1.0]),
         100.0,
-    ))));
+    )));
 
     // camera
     let focal_lenth = 1.0;
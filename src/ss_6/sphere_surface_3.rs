@@ -5,16 +5,15 @@ use ray_tracing_rust::sphere::Sphere;
 use ray_tracing_rust::utl;
 use ray_tracing_rust::vec3::{Point3, Vec3};
 use ray_tracing_rust::{hittable::HitRecord, hittable::HittableV2, hittable_list::HittableList};
-use std::cell::RefCell;
 use std::error::Error;
 use std::fs::File;
 use std::io::Write;
-use std::rc::Rc;
+use std::sync::Arc;
 
 fn ray_color<T: HittableV2>(ray: &Ray, world: &T) -> Color {
     let mut rec = HitRecord::new();
     if world.hit_v2(
-        &ray,
+        ray,
         Interval::new_by_value(0.0, utl::constans::INFINITY),
         &mut rec,
     ) {
@@ -44,14 +43,14 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // world
     let mut world = HittableList::new();
-    world.add(Rc::new(RefCell::new(Sphere::new(
+    world.add(Arc::new(Sphere::new(
         &Point3::from_slice([0.0, 0.0, -1.0]),
         0.5,
-    ))));
-    world.add(Rc::new(RefCell::new(Sphere::new(
+    )));
+    world.add(Arc::new(Sphere::new(
         &Point3::from_slice([0.0, -100.5, -1.0]),
         100.0,
-    ))));
+    )));
 
     // camera
     let focal_lenth = 1.0;
@@ -1,10 +1,12 @@
 use ray_tracing_rust::camera::Camera;
 use ray_tracing_rust::hittable_list::HittableList;
+use ray_tracing_rust::image_writer::save_image;
+use ray_tracing_rust::integrator::HemisphereIntegrator;
 use ray_tracing_rust::sphere::Sphere;
+use ray_tracing_rust::tone_map::ToneMap;
 use ray_tracing_rust::vec3::Point3;
-use std::cell::RefCell;
 use std::error::Error;
-use std::rc::Rc;
+use std::sync::Arc;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let argv = std::env::args().collect::<Vec<String>>();
@@ -14,20 +16,21 @@ fn main() -> Result<(), Box<dyn Error>> {
     let file_name = argv[1].clone();
 
     let mut world = HittableList::new();
-    world.add(Rc::new(RefCell::new(Sphere::new(
+    world.add(Arc::new(Sphere::new(
         &Point3::from_slice([0.0, 0.0, -1.0]),
         0.5,
-    ))));
-    world.add(Rc::new(RefCell::new(Sphere::new(
+    )));
+    world.add(Arc::new(Sphere::new(
         &Point3::from_slice([0.0, -100.5, -1.0]),
         100.0,
-    ))));
+    )));
 
     let mut camera = Camera::new();
     camera.aspect_ratio = 16.0 / 9.0;
     camera.image_width = 400;
     camera.samples_per_pixel = 100;
-    camera.render_diffuse(&world, &file_name)?;
+    let framebuffer = camera.render(&world, &HemisphereIntegrator::new(0.0));
+    save_image(&framebuffer, &file_name, &ToneMap::new())?;
 
     Ok(())
 }
@@ -0,0 +1,325 @@
+use crate::color::Color;
+use crate::material::{Lambertian, Material};
+use crate::triangle_mesh::TriangleMesh;
+use crate::vec3::{Point3, Vec3};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::sync::Arc;
+
+/// read *.stl meshes, ASCII or binary
+///
+/// the facet normals of the file are ignored, normals are computed from the vertices
+/// (counter-clockwise is the outside).
+pub struct StlLoader {
+    /// material of the mesh
+    pub mat: Option<Arc<dyn Material>>,
+    /// merge vertices whose positions snap to the same point of a grid of this size
+    /// (0.0: identical positions only, None: every facet keeps its own vertices)
+    pub weld_tolerance: Option<f64>,
+    /// shading normals averaged over the facets sharing a welded vertex
+    /// (false: flat facets)
+    pub smooth_normals: bool,
+}
+
+impl Default for StlLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StlLoader {
+    /// gray Lambertian, identical vertices welded, flat normals
+    pub fn new() -> Self {
+        Self {
+            mat: Some(Arc::new(Lambertian::new(&Color::from_slice([
+                0.5, 0.5, 0.5,
+            ])))),
+            weld_tolerance: Some(0.0),
+            smooth_normals: false,
+        }
+    }
+
+    pub fn load(&self, file_name: &str) -> Result<TriangleMesh, Box<dyn Error>> {
+        let mut input =
+            BufReader::new(File::open(file_name).map_err(|e| format!("{file_name}: {e}"))?);
+        self.read(&mut input)
+            .map_err(|e| format!("{file_name}: {e}").into())
+    }
+
+    pub fn read(&self, input: &mut dyn Read) -> Result<TriangleMesh, Box<dyn Error>> {
+        let mut data = vec![];
+        input.read_to_end(&mut data)?;
+        let triangles = if is_binary(&data) {
+            read_binary(&data)?
+        } else {
+            read_ascii(&data)?
+        };
+
+        Ok(self.build(triangles))
+    }
+
+    fn build(&self, triangles: Vec<[Point3; 3]>) -> TriangleMesh {
+        let mut positions = Vec::with_capacity(3 * triangles.len());
+        let mut indices = Vec::with_capacity(triangles.len());
+        let mut welded: HashMap<[i64; 3], u32> = HashMap::new();
+
+        for triangle in triangles {
+            let [a, b, c] = triangle.map(|position| match self.weld_tolerance {
+                Some(tolerance) => {
+                    let key = weld_key(&position, tolerance);
+                    *welded.entry(key).or_insert_with(|| {
+                        positions.push(position);
+                        positions.len() as u32 - 1
+                    })
+                }
+                None => {
+                    positions.push(position);
+                    positions.len() as u32 - 1
+                }
+            });
+            // facets that collapsed by welding
+            if a != b && b != c && c != a {
+                indices.push([a, b, c]);
+            }
+        }
+
+        let normals = self.smooth_normals.then(|| {
+            // the cross product is twice the area: larger facets weigh more
+            let mut normals = vec![Vec3::new(); positions.len()];
+            for &[a, b, c] in indices.iter() {
+                let [pa, pb, pc] = [a, b, c].map(|k| &positions[k as usize]);
+                let normal = (pb.clone() - pa.clone()).cross(&(pc.clone() - pa.clone()));
+                for k in [a, b, c] {
+                    normals[k as usize] += normal.clone();
+                }
+            }
+            normals
+                .into_iter()
+                .map(|normal| {
+                    if normal.near_zero() {
+                        normal
+                    } else {
+                        Vec3::new_unit_vec(normal)
+                    }
+                })
+                .collect()
+        });
+
+        TriangleMesh::new_with_attributes(positions, normals, None, None, indices, self.mat.clone())
+    }
+}
+
+fn weld_key(position: &Point3, tolerance: f64) -> [i64; 3] {
+    position.e.map(|x| {
+        if tolerance > 0.0 {
+            (x / tolerance).round() as i64
+        } else {
+            // -0.0 and 0.0 are the same position
+            (x + 0.0).to_bits() as i64
+        }
+    })
+}
+
+/// binary files have an 80 byte header and a triangle count that matches their size
+/// (ASCII files start with "solid", but so do some binary files)
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() >= 84 {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as u64;
+        if data.len() as u64 == 84 + 50 * count {
+            return true;
+        }
+    }
+
+    !data.trim_ascii_start().starts_with(b"solid")
+}
+
+fn read_binary(data: &[u8]) -> Result<Vec<[Point3; 3]>, Box<dyn Error>> {
+    if data.len() < 84 {
+        return Err(format!("unexpected end of data: {} bytes of header", data.len()).into());
+    }
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    let records = &data[84..];
+    if records.len() / 50 < count {
+        return Err(format!(
+            "unexpected end of data: {} bytes of triangles, expected {}",
+            records.len(),
+            50 * count
+        )
+        .into());
+    }
+
+    // normal, three vertices (little endian f32) and a 2 byte attribute
+    let triangles = records
+        .chunks_exact(50)
+        .take(count)
+        .map(|record| {
+            let value = |k: usize| {
+                let b = &record[4 * k..4 * k + 4];
+                f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64
+            };
+            [1, 2, 3]
+                .map(|v| Point3::from_slice([value(3 * v), value(3 * v + 1), value(3 * v + 2)]))
+        })
+        .collect();
+
+    Ok(triangles)
+}
+
+fn read_ascii(data: &[u8]) -> Result<Vec<[Point3; 3]>, Box<dyn Error>> {
+    let source = String::from_utf8_lossy(data);
+    let mut triangles = vec![];
+    let mut vertices = vec![];
+    let mut in_facet = false;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: String| format!("line {}: {message}", line_index + 1);
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+        match tokens[..] {
+            ["facet", ..] => {
+                if in_facet {
+                    return Err(error("facet inside a facet".to_string()).into());
+                }
+                in_facet = true;
+                vertices.clear();
+            }
+            ["vertex", x, y, z] => {
+                if !in_facet {
+                    return Err(error("vertex outside a facet".to_string()).into());
+                }
+                let coordinates = [x, y, z].map(|c| {
+                    c.parse::<f64>()
+                        .map_err(|_| error(format!("invalid number {c:?}")))
+                });
+                let [x, y, z] = coordinates;
+                vertices.push(Point3::from_slice([x?, y?, z?]));
+            }
+            ["vertex", ..] => return Err(error("expected 3 coordinates".to_string()).into()),
+            ["endfacet"] => {
+                if vertices.len() != 3 {
+                    return Err(error(format!(
+                        "a facet has {} vertices, expected 3",
+                        vertices.len()
+                    ))
+                    .into());
+                }
+                triangles.push([
+                    vertices[0].clone(),
+                    vertices[1].clone(),
+                    vertices[2].clone(),
+                ]);
+                in_facet = false;
+            }
+            _ => {}
+        }
+    }
+    if in_facet {
+        return Err("unexpected end of data in a facet".into());
+    }
+
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable_material::{HitRecordMat, HittableAaBb};
+    use crate::interval::consts;
+    use crate::ray::Ray;
+
+    /// tetrahedron with the corners at the origin and on the axes
+    fn tetrahedron() -> Vec<[[f32; 3]; 3]> {
+        let (o, x, y, z) = ([0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]);
+        vec![[o, y, x], [o, x, z], [o, z, y], [x, y, z]]
+    }
+
+    fn ascii() -> String {
+        let mut source = "solid tetrahedron\n".to_string();
+        for triangle in tetrahedron() {
+            source += "  facet normal 0 0 0\n    outer loop\n";
+            for [x, y, z] in triangle {
+                source += &format!("      vertex {x} {y} {z}\n");
+            }
+            source += "    endloop\n  endfacet\n";
+        }
+        source + "endsolid tetrahedron\n"
+    }
+
+    fn binary() -> Vec<u8> {
+        // a header starting with "solid" must not be taken for ASCII
+        let mut data = b"solid but binary".to_vec();
+        data.resize(80, 0);
+        data.extend_from_slice(&4u32.to_le_bytes());
+        for triangle in tetrahedron() {
+            data.extend_from_slice(&[0; 12]);
+            for value in triangle.iter().flatten() {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data.extend_from_slice(&[0, 0]);
+        }
+        data
+    }
+
+    #[test]
+    fn test_stl_reader() {
+        let ray = Ray::from_origin_dir(
+            &Point3::from_slice([0.2, 0.3, 2.0]),
+            &Vec3::from_slice([0.0, 0.0, -1.0]),
+        );
+        for data in [ascii().into_bytes(), binary()] {
+            let mut loader = StlLoader::new();
+            let mesh = loader.read(&mut &data[..]).unwrap();
+            assert_eq!(mesh.n_triangles(), 4);
+            assert_eq!(mesh.n_vertices(), 4);
+
+            let mut rec = HitRecordMat::new();
+            assert!(mesh.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+            assert!((rec.t - 1.5).abs() < 1e-6);
+            assert!(rec.front_face);
+            let flat = Vec3::new_unit_vec(Vec3::from_slice([1.0, 1.0, 1.0]));
+            assert!((rec.normal.clone() - flat.clone()).norm() < 1e-6);
+
+            loader.smooth_normals = true;
+            let mesh = loader.read(&mut &data[..]).unwrap();
+            assert!(mesh.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+            assert!((rec.normal.clone() - flat).norm() > 0.1);
+
+            loader.weld_tolerance = None;
+            assert_eq!(loader.read(&mut &data[..]).unwrap().n_vertices(), 12);
+        }
+    }
+
+    #[test]
+    fn test_stl_errors() {
+        for (data, message) in [
+            (
+                &b"solid x\nfacet normal 0 0 1\nvertex 0 0\n"[..],
+                "line 3: expected 3 coordinates",
+            ),
+            (
+                b"solid x\nfacet\nvertex 0 0 0\nendfacet\n",
+                "line 4: a facet has 1 vertices",
+            ),
+            (
+                b"solid x\nfacet\nvertex 0 0 z\n",
+                "line 3: invalid number \"z\"",
+            ),
+            (b"solid x\nfacet\n", "unexpected end of data in a facet"),
+            (b"\x00\x01", "unexpected end of data: 2 bytes of header"),
+        ] {
+            let error = StlLoader::new().read(&mut &data[..]).err().unwrap();
+            assert!(error.to_string().starts_with(message), "{error}");
+        }
+
+        // without "solid" a file with the wrong size is binary
+        let mut data = binary();
+        data[..5].copy_from_slice(b"model");
+        data.truncate(100);
+        let error = StlLoader::new().read(&mut &data[..]).err().unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("unexpected end of data: 16 bytes of triangles"));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::color::{linear_to_gamma, Color};
+
+/// maps HDR radiance to displayable [0, 1] values (applied per channel)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMapOperator {
+    /// no compression, values above 1.0 are clipped
+    Clamp,
+    /// x / (1 + x)
+    Reinhard,
+    /// Reinhard that maps `white` (and above) to 1.0
+    ReinhardExtended { white: f64 },
+    /// ACES filmic curve fitted by Krzysztof Narkowicz
+    Aces,
+    /// John Hable's Uncharted 2 filmic curve, `white` is the linear white point
+    Hable { white: f64 },
+}
+
+/// encoding of the tone mapped values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transfer {
+    /// no encoding (same as `write_color`)
+    Linear,
+    /// x^{\gamma} (same as `write_color_gamma`)
+    Gamma(f64),
+    /// sRGB transfer function (IEC 61966-2-1)
+    Srgb,
+}
+
+/// conversion from the HDR framebuffer to integer output samples:
+/// exposure, tone mapping operator, transfer function and quantization
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToneMap {
+    pub operator: ToneMapOperator,
+    /// exposure in stops (EV): colors are scaled by 2^exposure
+    pub exposure: f64,
+    pub transfer: Transfer,
+}
+
+impl Default for ToneMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToneMap {
+    /// colors as they are (by `write_color`)
+    pub fn new() -> Self {
+        Self {
+            operator: ToneMapOperator::Clamp,
+            exposure: 0.0,
+            transfer: Transfer::Linear,
+        }
+    }
+
+    /// gamma correction only (by `write_color_gamma`)
+    pub fn new_gamma(gamma: f64) -> Self {
+        Self {
+            transfer: Transfer::Gamma(gamma),
+            ..Self::new()
+        }
+    }
+
+    /// `operator` with the sRGB transfer function
+    pub fn new_srgb(operator: ToneMapOperator) -> Self {
+        Self {
+            operator,
+            exposure: 0.0,
+            transfer: Transfer::Srgb,
+        }
+    }
+
+    /// exposure and tone mapping operator: still linear, in [0, 1] except for `Clamp`
+    pub fn map(&self, color: &Color) -> Color {
+        let scale = 2f64.powf(self.exposure);
+        let [r, g, b] =
+            [color.x(), color.y(), color.z()].map(|c| self.map_component((scale * c).max(0.0)));
+
+        Color::from_slice([r, g, b])
+    }
+
+    fn map_component(&self, x: f64) -> f64 {
+        match self.operator {
+            ToneMapOperator::Clamp => x,
+            ToneMapOperator::Reinhard => x / (1.0 + x),
+            ToneMapOperator::ReinhardExtended { white } => {
+                (x * (1.0 + x / (white * white)) / (1.0 + x)).min(1.0)
+            }
+            ToneMapOperator::Aces => {
+                let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                ((x * (a * x + b)) / (x * (c * x + d) + e)).clamp(0.0, 1.0)
+            }
+            ToneMapOperator::Hable { white } => {
+                // exposure bias 2.0 as in the original
+                (hable_partial(2.0 * x) / hable_partial(white)).min(1.0)
+            }
+        }
+    }
+
+    /// tone map and encode `color` to integer samples in [0, max_value]
+    pub fn encode(&self, color: &Color, max_value: u16) -> [u16; 3] {
+        let color = self.map(color);
+        [color.x(), color.y(), color.z()].map(|c| match self.transfer {
+            Transfer::Linear => quantize(c, None, max_value),
+            Transfer::Gamma(gamma) => quantize(c, Some(gamma), max_value),
+            Transfer::Srgb => quantize(linear_to_srgb(c), Some(1.0), max_value),
+        })
+    }
+}
+
+fn hable_partial(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+/// sRGB opto-electronic transfer function
+pub fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// inverse of `linear_to_srgb`
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// convert a component to an integer sample in [0, max_value]
+///
+/// for 8-bit this gives the same values as `write_color` (gamma is None)
+/// and `write_color_gamma`.
+fn quantize(c: f64, gamma: Option<f64>, max_value: u16) -> u16 {
+    let max_value = max_value as f64;
+    match gamma {
+        Some(gamma) => {
+            let c = linear_to_gamma(c, gamma);
+            ((max_value + 1.0) * c.clamp(0.0, (max_value + 0.999) / (max_value + 1.0))) as u16
+        }
+        None => ((max_value + 0.999) * c).clamp(0.0, max_value) as u16,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{write_color, write_color_gamma};
+
+    #[test]
+    fn test_same_as_write_color() {
+        for k in 0..=1000 {
+            let c = k as f64 / 1000.0;
+            let color = Color::from_slice([c, c, c]);
+
+            let mut expected = vec![];
+            write_color(&mut expected, &color).unwrap();
+            let [r, g, b] = ToneMap::new().encode(&color, 255);
+            assert_eq!(expected, format!("{r} {g} {b}\n").into_bytes());
+
+            let mut expected = vec![];
+            write_color_gamma(0.5, &mut expected, &color).unwrap();
+            let [r, g, b] = ToneMap::new_gamma(0.5).encode(&color, 255);
+            assert_eq!(expected, format!("{r} {g} {b}\n").into_bytes());
+        }
+    }
+
+    #[test]
+    fn test_operators() {
+        let operators = [
+            ToneMapOperator::Reinhard,
+            ToneMapOperator::ReinhardExtended { white: 4.0 },
+            ToneMapOperator::Aces,
+            ToneMapOperator::Hable { white: 11.2 },
+        ];
+        for operator in operators {
+            let tone_map = ToneMap::new_srgb(operator);
+            let mut previous = -1.0;
+            for k in 0..=100 {
+                let x = 0.1 * k as f64;
+                let y = tone_map.map(&Color::from_slice([x, x, x])).x();
+                assert!((0.0..=1.0).contains(&y), "{operator:?} {x} {y}");
+                assert!(y >= previous, "{operator:?} is not monotonic at {x}");
+                previous = y;
+            }
+            assert_eq!(tone_map.map(&Color::new()).x(), 0.0, "{operator:?}");
+            assert_eq!(
+                tone_map.encode(&Color::from_slice([1e9, 1e9, 1e9]), 255),
+                [255; 3]
+            );
+        }
+
+        let white = ToneMap::new_srgb(ToneMapOperator::ReinhardExtended { white: 4.0 });
+        assert!((white.map(&Color::from_slice([4.0, 4.0, 4.0])).x() - 1.0).abs() < 1e-12);
+        let hable = ToneMap::new_srgb(ToneMapOperator::Hable { white: 11.2 });
+        assert!((hable.map(&Color::from_slice([5.6, 5.6, 5.6])).x() - 1.0).abs() < 1e-12);
+
+        // +1 EV doubles the radiance before the operator
+        let mut reinhard = ToneMap::new_srgb(ToneMapOperator::Reinhard);
+        reinhard.exposure = 1.0;
+        assert!((reinhard.map(&Color::from_slice([0.5, 0.5, 0.5])).x() - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_srgb() {
+        for k in 0..=100 {
+            let c = k as f64 / 100.0;
+            assert!((srgb_to_linear(linear_to_srgb(c)) - c).abs() < 1e-12);
+        }
+        assert_eq!(
+            ToneMap::new_srgb(ToneMapOperator::Clamp)
+                .encode(&Color::from_slice([0.0, 0.214, 1.0]), 255),
+            [0, 127, 255]
+        );
+    }
+}
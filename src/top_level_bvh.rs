@@ -0,0 +1,155 @@
+use crate::aabb::AaBb;
+use crate::bvh::{BuildPrimitive, BvhOptions};
+use crate::bvh_linear::{self, LinearBvhNode};
+use crate::hittable_material::{HitRecordMat, HittableAaBb, HittableMat};
+use crate::instance::Instance;
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+/// top level of a two-level acceleration structure: a BVH over placed instances
+///
+/// the instances share their bottom-level objects (`TriangleMesh`, `LinearBvh` of spheres...),
+/// so placing an object many times costs one transform per placement.
+/// a `TopLevelBvh` is itself an object, so it can be instanced by another one.
+pub struct TopLevelBvh {
+    nodes: Vec<LinearBvhNode>,
+    instances: Vec<Instance>,
+}
+
+impl TopLevelBvh {
+    /// build with default options (binned SAH)
+    pub fn new(instances: Vec<Instance>) -> Self {
+        Self::new_with_options(instances, &BvhOptions::new())
+    }
+
+    pub fn new_with_options(instances: Vec<Instance>, options: &BvhOptions) -> Self {
+        let build_primitives = instances
+            .into_iter()
+            .map(|instance| {
+                let bbox = instance.bounding_box();
+                BuildPrimitive::new(instance, bbox)
+            })
+            .collect();
+        let (nodes, instances) = bvh_linear::build_nodes(build_primitives, options);
+
+        Self { nodes, instances }
+    }
+
+    pub fn n_instances(&self) -> usize {
+        self.instances.len()
+    }
+}
+
+impl HittableAaBb for TopLevelBvh {
+    fn hit_aabb(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        bvh_linear::hit_nodes(&self.nodes, ray, ray_t, |k, ray_t| {
+            self.instances[k].hit_aabb(ray, ray_t, rec).then_some(rec.t)
+        })
+    }
+
+    fn bounding_box(&self) -> AaBb {
+        bvh_linear::nodes_bounding_box(&self.nodes)
+    }
+}
+
+impl HittableMat for TopLevelBvh {
+    fn hit_mat(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        self.hit_aabb(ray, ray_t, rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bvh_linear::LinearBvh;
+    use crate::color::Color;
+    use crate::hittable_list_aabb::HittableListAaBb;
+    use crate::material::{Lambertian, Material};
+    use crate::sphere_aabb::SphereAaBb;
+    use crate::transform::Transform;
+    use crate::utl;
+    use crate::vec3::{Point3, Vec3};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_top_level_bvh_hit_same_as_list() {
+        let mut random = utl::Random::new_by_seed(22);
+        // bottom level: a small group of spheres
+        let mut group: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+        for center in [[0.0, 0.0, 0.0], [0.0, 0.5, 0.0], [0.3, 0.2, 0.1]] {
+            group.add(Arc::new(SphereAaBb::new_stationary(
+                &Point3::from_slice(center),
+                0.2,
+                None,
+            )));
+        }
+        let group: Arc<dyn HittableAaBb> = Arc::new(LinearBvh::new(group));
+        let red: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::from_slice([1.0, 0.0, 0.0])));
+
+        let placements = (-5..5)
+            .flat_map(|a| (-5..5).map(move |b| (a, b)))
+            .map(|(a, b)| {
+                Transform::translate(&Vec3::from_slice([a as f64, 0.0, b as f64]))
+                    * Transform::rotate_y(36.0 * (a + b) as f64)
+                    * Transform::scale(&Vec3::from_slice([1.0, 0.5 + 0.1 * (b + 5) as f64, 1.0]))
+            })
+            .collect::<Vec<_>>();
+        let instances = |override_mat: bool| {
+            placements
+                .iter()
+                .enumerate()
+                .map(|(k, transform)| {
+                    let mat = (override_mat && k % 2 == 0).then(|| red.clone());
+                    Instance::new_with_material(group.clone(), transform.clone(), mat)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut list: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+        for instance in instances(false) {
+            list.add(Arc::new(instance));
+        }
+        let top_level = TopLevelBvh::new(instances(true));
+        assert_eq!(top_level.n_instances(), 100);
+        // every instance refers to the same bottom level
+        assert_eq!(Arc::strong_count(&group), 201);
+
+        let origin = Point3::from_slice([13.0, 2.0, 3.0]);
+        let mut overridden = 0;
+        for _ in 0..1000 {
+            let target = Point3::from_slice([
+                random.random_f64_range(-6.0, 6.0),
+                random.random_f64_range(-1.0, 2.0),
+                random.random_f64_range(-6.0, 6.0),
+            ]);
+            let ray = Ray::from_origin_dir(&origin, &(target - origin.clone()));
+
+            let mut rec_list = HitRecordMat::new();
+            let mut rec_bvh = HitRecordMat::new();
+            let ray_t = Interval::new_by_value(0.001, utl::constans::INFINITY);
+            let hit_list = list.hit_aabb(&ray, ray_t.clone(), &mut rec_list);
+            let hit_bvh = top_level.hit_aabb(&ray, ray_t, &mut rec_bvh);
+
+            assert_eq!(hit_list, hit_bvh);
+            if hit_list {
+                assert!((rec_list.t - rec_bvh.t).abs() < 1e-12);
+                assert!((rec_list.normal.clone() - rec_bvh.normal.clone()).norm() < 1e-12);
+                assert!(rec_list.mat.is_none());
+                if let Some(mat) = &rec_bvh.mat {
+                    assert!(Arc::ptr_eq(mat, &red));
+                    overridden += 1;
+                }
+            }
+        }
+        assert!(overridden > 0);
+
+        // a top level can be instanced again
+        let bbox = top_level.bounding_box();
+        let nested = TopLevelBvh::new(vec![Instance::new(
+            Arc::new(top_level),
+            Transform::translate(&Vec3::from_slice([0.0, 10.0, 0.0])),
+        )]);
+        let nested_bbox = nested.bounding_box();
+        assert!((nested_bbox.y.min - bbox.y.min - 10.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::aabb::AaBb;
+use crate::interval::Interval;
+use crate::vec3::{Point3, Vec3};
+use std::error::Error;
+use std::ops::Mul;
+
+/// affine transform: 3x4 matrix (the last row of the 4x4 matrix is 0 0 0 1) with its inverse
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    m: [[f64; 4]; 3],
+    m_inv: [[f64; 4]; 3],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        let m = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+        ];
+        Self { m, m_inv: m }
+    }
+
+    /// rows of a 3x4 matrix, error if it is not invertible
+    pub fn from_matrix3x4(m: [[f64; 4]; 3]) -> Result<Self, Box<dyn Error>> {
+        let m_inv = inverse(&m).ok_or("singular matrix")?;
+        Ok(Self { m, m_inv })
+    }
+
+    /// rows of a 4x4 matrix, error if it is not affine or not invertible
+    pub fn from_matrix4x4(m: [[f64; 4]; 4]) -> Result<Self, Box<dyn Error>> {
+        if m[3] != [0.0, 0.0, 0.0, 1.0] {
+            return Err(format!("not an affine transform (last row {:?})", m[3]).into());
+        }
+        Self::from_matrix3x4([m[0], m[1], m[2]])
+    }
+
+    pub fn translate(offset: &Vec3) -> Self {
+        let [x, y, z] = offset.e;
+        Self {
+            m: [[1.0, 0.0, 0.0, x], [0.0, 1.0, 0.0, y], [0.0, 0.0, 1.0, z]],
+            m_inv: [
+                [1.0, 0.0, 0.0, -x],
+                [0.0, 1.0, 0.0, -y],
+                [0.0, 0.0, 1.0, -z],
+            ],
+        }
+    }
+
+    /// scale along the axes (the factors must not be 0)
+    pub fn scale(factors: &Vec3) -> Self {
+        let [x, y, z] = factors.e;
+        Self {
+            m: [[x, 0.0, 0.0, 0.0], [0.0, y, 0.0, 0.0], [0.0, 0.0, z, 0.0]],
+            m_inv: [
+                [1.0 / x, 0.0, 0.0, 0.0],
+                [0.0, 1.0 / y, 0.0, 0.0],
+                [0.0, 0.0, 1.0 / z, 0.0],
+            ],
+        }
+    }
+
+    /// rotation by `degrees` around `axis` (counterclockwise looking against the axis)
+    pub fn rotate(axis: &Vec3, degrees: f64) -> Self {
+        let [x, y, z] = Vec3::new_unit_vec(axis.clone()).e;
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self::from_rotation([
+            [
+                x * x + (1.0 - x * x) * cos,
+                x * y * (1.0 - cos) - z * sin,
+                x * z * (1.0 - cos) + y * sin,
+            ],
+            [
+                x * y * (1.0 - cos) + z * sin,
+                y * y + (1.0 - y * y) * cos,
+                y * z * (1.0 - cos) - x * sin,
+            ],
+            [
+                x * z * (1.0 - cos) - y * sin,
+                y * z * (1.0 - cos) + x * sin,
+                z * z + (1.0 - z * z) * cos,
+            ],
+        ])
+    }
+
+    /// rows of an orthonormal 3x3 matrix
+    pub(crate) fn from_rotation(r: [[f64; 3]; 3]) -> Self {
+        let m = r.map(|[a, b, c]| [a, b, c, 0.0]);
+        // the inverse of a rotation is its transpose
+        let m_inv = [0, 1, 2].map(|i| [r[0][i], r[1][i], r[2][i], 0.0]);
+
+        Self { m, m_inv }
+    }
+
+    pub fn rotate_x(degrees: f64) -> Self {
+        Self::rotate(&Vec3::from_slice([1.0, 0.0, 0.0]), degrees)
+    }
+
+    pub fn rotate_y(degrees: f64) -> Self {
+        Self::rotate(&Vec3::from_slice([0.0, 1.0, 0.0]), degrees)
+    }
+
+    pub fn rotate_z(degrees: f64) -> Self {
+        Self::rotate(&Vec3::from_slice([0.0, 0.0, 1.0]), degrees)
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            m: self.m_inv,
+            m_inv: self.m,
+        }
+    }
+
+    /// rows of the 4x4 matrix
+    pub fn matrix4x4(&self) -> [[f64; 4]; 4] {
+        [self.m[0], self.m[1], self.m[2], [0.0, 0.0, 0.0, 1.0]]
+    }
+
+    pub fn point(&self, p: &Point3) -> Point3 {
+        Point3::from_slice(
+            self.m
+                .map(|row| row[0] * p.x() + row[1] * p.y() + row[2] * p.z() + row[3]),
+        )
+    }
+
+    /// directions are not translated
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        Vec3::from_slice(
+            self.m
+                .map(|row| row[0] * v.x() + row[1] * v.y() + row[2] * v.z()),
+        )
+    }
+
+    /// normals are transformed by the inverse transpose (the result is not normalized)
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        let m_inv = &self.m_inv;
+        Vec3::from_slice(
+            [0, 1, 2].map(|i| m_inv[0][i] * n.x() + m_inv[1][i] * n.y() + m_inv[2][i] * n.z()),
+        )
+    }
+
+    /// box around the transformed corners of `bbox`
+    pub fn aabb(&self, bbox: &AaBb) -> AaBb {
+        if bbox.x.size() < 0.0 || bbox.y.size() < 0.0 || bbox.z.size() < 0.0 {
+            // empty
+            return bbox.clone();
+        }
+
+        let mut intervals = [Interval::new(), Interval::new(), Interval::new()];
+        for corner in 0..8 {
+            let p = self.point(&Point3::from_slice([
+                if corner & 1 == 0 {
+                    bbox.x.min
+                } else {
+                    bbox.x.max
+                },
+                if corner & 2 == 0 {
+                    bbox.y.min
+                } else {
+                    bbox.y.max
+                },
+                if corner & 4 == 0 {
+                    bbox.z.min
+                } else {
+                    bbox.z.max
+                },
+            ]));
+            for (axis, interval) in intervals.iter_mut().enumerate() {
+                *interval = Interval::new_by_two_intervals(
+                    interval,
+                    &Interval::new_by_value(p[axis], p[axis]),
+                );
+            }
+        }
+        let [x, y, z] = intervals;
+
+        AaBb::new_by_values(&x, &y, &z)
+    }
+}
+
+/// `a * b` applies `b` first, then `a`
+impl Mul for Transform {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            m: multiply(&self.m, &rhs.m),
+            m_inv: multiply(&rhs.m_inv, &self.m_inv),
+        }
+    }
+}
+
+fn multiply(a: &[[f64; 4]; 3], b: &[[f64; 4]; 3]) -> [[f64; 4]; 3] {
+    a.map(|row| {
+        let mut product =
+            [0, 1, 2, 3].map(|j| row[0] * b[0][j] + row[1] * b[1][j] + row[2] * b[2][j]);
+        product[3] += row[3];
+        product
+    })
+}
+
+/// inverse of the affine transform, None if it is singular
+fn inverse(m: &[[f64; 4]; 3]) -> Option<[[f64; 4]; 3]> {
+    // inverse of the 3x3 part by the adjugate
+    let cofactor = |i: usize, j: usize| {
+        let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+        let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+        m[i1][j1] * m[i2][j2] - m[i1][j2] * m[i2][j1]
+    };
+    let det = m[0][0] * cofactor(0, 0) + m[0][1] * cofactor(0, 1) + m[0][2] * cofactor(0, 2);
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+
+    let mut m_inv = [[0.0; 4]; 3];
+    for (i, row) in m_inv.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().take(3).enumerate() {
+            *value = cofactor(j, i) / det;
+        }
+    }
+    // the translation is undone after the linear part
+    for row in m_inv.iter_mut() {
+        row[3] = -(0..3).map(|j| row[j] * m[j][3]).sum::<f64>();
+    }
+
+    Some(m_inv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: &Vec3, b: [f64; 3]) {
+        assert!(
+            (a.clone() - Vec3::from_slice(b)).norm() < 1e-12,
+            "{a} {b:?}"
+        );
+    }
+
+    #[test]
+    fn test_transform() {
+        let rotate = Transform::rotate_z(90.0);
+        assert_near(
+            &rotate.point(&Point3::from_slice([1.0, 0.0, 0.0])),
+            [0.0, 1.0, 0.0],
+        );
+        let rotate = Transform::rotate_y(90.0);
+        assert_near(
+            &rotate.point(&Point3::from_slice([0.0, 0.0, 1.0])),
+            [1.0, 0.0, 0.0],
+        );
+
+        let transform = Transform::translate(&Vec3::from_slice([1.0, 2.0, 3.0]))
+            * Transform::rotate(&Vec3::from_slice([1.0, 1.0, 0.0]), 30.0)
+            * Transform::scale(&Vec3::from_slice([2.0, 0.5, 1.0]));
+        let p = Point3::from_slice([0.3, -0.7, 2.0]);
+        let q = transform.point(&p);
+        assert_near(&transform.inverse().point(&q), p.e);
+        // the inverse computed from the matrix is the same as the composed one
+        let from_matrix = Transform::from_matrix4x4(transform.matrix4x4()).unwrap();
+        assert_near(&from_matrix.inverse().point(&q), p.e);
+
+        // normals stay perpendicular to the transformed surface
+        let (tangent, normal) = (
+            Vec3::from_slice([1.0, 1.0, 0.0]),
+            Vec3::from_slice([1.0, -1.0, 0.0]),
+        );
+        assert!(
+            transform
+                .vector(&tangent)
+                .dot(&transform.normal(&normal))
+                .abs()
+                < 1e-12
+        );
+        // points are translated, vectors are not
+        assert_near(
+            &Transform::translate(&Vec3::from_slice([1.0, 2.0, 3.0])).vector(&tangent),
+            tangent.e,
+        );
+
+        assert!(Transform::from_matrix3x4([
+            [1.0, 2.0, 3.0, 0.0],
+            [2.0, 4.0, 6.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0]
+        ])
+        .is_err());
+        let mut m = Transform::identity().matrix4x4();
+        m[3][0] = 1.0;
+        assert!(Transform::from_matrix4x4(m).is_err());
+    }
+
+    #[test]
+    fn test_aabb() {
+        let bbox = AaBb::new_by_two_points(
+            &Point3::from_slice([-1.0, -1.0, -1.0]),
+            &Point3::from_slice([1.0, 1.0, 1.0]),
+        );
+        let rotated = Transform::rotate_z(45.0).aabb(&bbox);
+        let half_diagonal = 2f64.sqrt();
+        assert!((rotated.x.max - half_diagonal).abs() < 1e-12);
+        assert!((rotated.y.min + half_diagonal).abs() < 1e-12);
+        assert!((rotated.z.max - 1.0).abs() < 1e-12);
+
+        let moved = Transform::translate(&Vec3::from_slice([5.0, 0.0, 0.0])).aabb(&bbox);
+        assert_eq!((moved.x.min, moved.x.max), (4.0, 6.0));
+    }
+}
@@ -0,0 +1,247 @@
+use crate::aabb::AaBb;
+use crate::hittable_material::{HitRecordMat, HittableAaBb, HittableMat};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+/// triangle with optional per vertex shading normals and texture coordinates
+pub struct Triangle {
+    vertices: [Point3; 3],
+    /// shading normals interpolated over the triangle (None: the geometric normal)
+    pub normals: Option<[Vec3; 3]>,
+    /// texture coordinates interpolated over the triangle (None: the barycentric coordinates)
+    pub uvs: Option<[[f64; 2]; 3]>,
+    mat: Option<Arc<dyn Material>>,
+    hbox: AaBb,
+}
+
+impl Triangle {
+    pub fn new(a: &Point3, b: &Point3, c: &Point3, mat: Option<Arc<dyn Material>>) -> Self {
+        Self {
+            vertices: [a.clone(), b.clone(), c.clone()],
+            normals: None,
+            uvs: None,
+            mat,
+            hbox: triangle_bounding_box(a, b, c),
+        }
+    }
+}
+
+impl HittableAaBb for Triangle {
+    fn hit_aabb(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        let [a, b, c] = &self.vertices;
+        let Some((t, barycentric)) = intersect_triangle(ray, &ray_t, a, b, c) else {
+            return false;
+        };
+
+        set_hit_record(
+            ray,
+            t,
+            barycentric,
+            &self.vertices,
+            self.normals.as_ref(),
+            self.uvs.as_ref(),
+            rec,
+        );
+        rec.mat = self.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> AaBb {
+        self.hbox.clone()
+    }
+}
+
+impl HittableMat for Triangle {
+    fn hit_mat(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        self.hit_aabb(ray, ray_t, rec)
+    }
+}
+
+/// box of the three vertices, padded because a triangle parallel to an axis is flat
+pub(crate) fn triangle_bounding_box(a: &Point3, b: &Point3, c: &Point3) -> AaBb {
+    AaBb::new_by_two_aabb(
+        &AaBb::new_by_two_points(a, b),
+        &AaBb::new_by_two_points(a, c),
+    )
+    .pad_to_minimums(0.0001)
+}
+
+/// watertight ray/triangle intersection (Woop, Benthin and Wald, JCGT 2013)
+///
+/// returns the ray parameter and the barycentric coordinates of the hit point.
+/// a ray through a shared edge or vertex hits at least one of the triangles.
+pub(crate) fn intersect_triangle(
+    ray: &Ray,
+    ray_t: &Interval,
+    a: &Point3,
+    b: &Point3,
+    c: &Point3,
+) -> Option<(f64, [f64; 3])> {
+    let dir = ray.direction();
+
+    // the axis where the direction is largest becomes z, keep the winding
+    let kz = (0..3)
+        .max_by(|&i, &j| dir[i].abs().total_cmp(&dir[j].abs()))
+        .unwrap();
+    let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+    if dir[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+    if dir[kz] == 0.0 {
+        return None;
+    }
+
+    // shear and scale so that the ray points along +z
+    let (sx, sy, sz) = (dir[kx] / dir[kz], dir[ky] / dir[kz], 1.0 / dir[kz]);
+    let (a, b, c) = (
+        a.clone() - ray.origin().clone(),
+        b.clone() - ray.origin().clone(),
+        c.clone() - ray.origin().clone(),
+    );
+    let (ax, ay) = (a[kx] - sx * a[kz], a[ky] - sy * a[kz]);
+    let (bx, by) = (b[kx] - sx * b[kz], b[ky] - sy * b[kz]);
+    let (cx, cy) = (c[kx] - sx * c[kz], c[ky] - sy * c[kz]);
+
+    // scaled barycentric coordinates: signed areas of the edges seen from the ray
+    let u = cx * by - cy * bx;
+    let v = ax * cy - ay * cx;
+    let w = bx * ay - by * ax;
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    let t = (u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz]) / det;
+    if !ray_t.surrounds(t) {
+        return None;
+    }
+
+    Some((t, [u / det, v / det, w / det]))
+}
+
+/// fill `rec` for a hit at `t`: geometric normal for the face, interpolated shading normal and uv
+pub(crate) fn set_hit_record(
+    ray: &Ray,
+    t: f64,
+    barycentric: [f64; 3],
+    vertices: &[Point3; 3],
+    normals: Option<&[Vec3; 3]>,
+    uvs: Option<&[[f64; 2]; 3]>,
+    rec: &mut HitRecordMat,
+) {
+    let [a, b, c] = vertices;
+    let [b0, b1, b2] = barycentric;
+    let outward_normal =
+        Vec3::new_unit_vec((b.clone() - a.clone()).cross(&(c.clone() - a.clone())));
+
+    rec.t = t;
+    rec.p = ray.at(t);
+    rec.barycentric = barycentric;
+    rec.color = None;
+    rec.set_face_normal(ray, &outward_normal);
+    if let Some([n0, n1, n2]) = normals {
+        let shading_normal =
+            Vec3::new_unit_vec(b0 * n0.clone() + b1 * n1.clone() + b2 * n2.clone());
+        // shading normals on the same side as the face normal
+        rec.normal = if shading_normal.dot(&rec.normal) < 0.0 {
+            -shading_normal
+        } else {
+            shading_normal
+        };
+    }
+    (rec.u, rec.v) = match uvs {
+        Some([uv0, uv1, uv2]) => (
+            b0 * uv0[0] + b1 * uv1[0] + b2 * uv2[0],
+            b0 * uv0[1] + b1 * uv1[1] + b2 * uv2[1],
+        ),
+        None => (b1, b2),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::consts;
+    use crate::utl;
+
+    #[test]
+    fn test_triangle_hit() {
+        let mut triangle = Triangle::new(
+            &Point3::from_slice([0.0, 0.0, -1.0]),
+            &Point3::from_slice([1.0, 0.0, -1.0]),
+            &Point3::from_slice([0.0, 1.0, -1.0]),
+            None,
+        );
+        triangle.normals = Some([
+            Vec3::from_slice([0.0, 0.0, 1.0]),
+            Vec3::from_slice([1.0, 0.0, 1.0]),
+            Vec3::from_slice([0.0, 1.0, 1.0]),
+        ]);
+        triangle.uvs = Some([[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]]);
+
+        let mut rec = HitRecordMat::new();
+        let ray = Ray::from_origin_dir(
+            &Point3::from_slice([0.25, 0.5, 0.0]),
+            &Vec3::from_slice([0.0, 0.0, -1.0]),
+        );
+        assert!(triangle.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        let [b0, b1, b2] = rec.barycentric;
+        assert!((b0 - 0.25).abs() < 1e-12 && (b1 - 0.25).abs() < 1e-12 && (b2 - 0.5).abs() < 1e-12);
+        assert!((rec.u - 0.75).abs() < 1e-12 && (rec.v - 0.5).abs() < 1e-12);
+        assert!(rec.front_face);
+        let expected = Vec3::new_unit_vec(Vec3::from_slice([0.25, 0.5, 1.0]));
+        assert!((rec.normal.clone() - expected).norm() < 1e-12);
+
+        // from behind the shading normal is flipped with the face
+        let ray = Ray::from_origin_dir(
+            &Point3::from_slice([0.25, 0.5, -2.0]),
+            &Vec3::from_slice([0.0, 0.0, 1.0]),
+        );
+        assert!(triangle.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+        assert!(!rec.front_face);
+        assert!(rec.normal.z() < 0.0);
+
+        let miss = Ray::from_origin_dir(
+            &Point3::from_slice([0.75, 0.5, 0.0]),
+            &Vec3::from_slice([0.0, 0.0, -1.0]),
+        );
+        assert!(!triangle.hit_aabb(&miss, consts::UNIVERSE, &mut rec));
+    }
+
+    #[test]
+    fn test_watertight() {
+        // two triangles sharing the diagonal of a square: rays through the diagonal never leak
+        let corners = [
+            Point3::from_slice([-1.0, -1.0, 0.3]),
+            Point3::from_slice([1.0, -1.0, -0.2]),
+            Point3::from_slice([1.0, 1.0, 0.1]),
+            Point3::from_slice([-1.0, 1.0, 0.0]),
+        ];
+        let triangles = [
+            Triangle::new(&corners[0], &corners[1], &corners[2], None),
+            Triangle::new(&corners[0], &corners[2], &corners[3], None),
+        ];
+
+        let mut random = utl::Random::new_by_seed(11);
+        let origin = Point3::from_slice([0.1, 0.2, 5.0]);
+        for _ in 0..10000 {
+            let s = random.random_f64_range(-0.9, 0.9);
+            let target = Point3::from_slice([s, s, 0.3 - 0.1 * (s + 1.0)]);
+            let ray = Ray::from_origin_dir(&origin, &(target - origin.clone()));
+            let mut rec = HitRecordMat::new();
+            assert!(triangles.iter().any(|triangle| triangle.hit_aabb(
+                &ray,
+                consts::UNIVERSE,
+                &mut rec
+            )));
+        }
+    }
+}
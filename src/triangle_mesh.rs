@@ -0,0 +1,262 @@
+use crate::aabb::AaBb;
+use crate::bvh::{BuildPrimitive, BvhOptions};
+use crate::bvh_linear::{self, LinearBvhNode};
+use crate::color::Color;
+use crate::hittable_material::{HitRecordMat, HittableAaBb, HittableMat};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::triangle;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+/// indexed triangle mesh: vertex attributes are shared by the triangles
+///
+/// the mesh has its own BVH over the triangles, so it is a single object in the scene.
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    /// per vertex shading normals
+    normals: Option<Vec<Vec3>>,
+    /// per vertex texture coordinates
+    uvs: Option<Vec<[f64; 2]>>,
+    /// per vertex colors (see `LambertianVertexColor`)
+    colors: Option<Vec<Color>>,
+    /// vertex indices of each triangle, in the order of the BVH leaves
+    indices: Vec<[u32; 3]>,
+    mat: Option<Arc<dyn Material>>,
+    nodes: Vec<LinearBvhNode>,
+}
+
+impl TriangleMesh {
+    /// panics if an index is out of range
+    pub fn new(
+        positions: Vec<Point3>,
+        indices: Vec<[u32; 3]>,
+        mat: Option<Arc<dyn Material>>,
+    ) -> Self {
+        Self::new_with_attributes(positions, None, None, None, indices, mat)
+    }
+
+    /// `normals`, `uvs` and `colors` have one entry per position.
+    /// panics if an index is out of range or the numbers of attributes differ
+    pub fn new_with_attributes(
+        positions: Vec<Point3>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<[f64; 2]>>,
+        colors: Option<Vec<Color>>,
+        indices: Vec<[u32; 3]>,
+        mat: Option<Arc<dyn Material>>,
+    ) -> Self {
+        let n_vertices = positions.len();
+        assert!(
+            normals
+                .as_ref()
+                .is_none_or(|normals| normals.len() == n_vertices),
+            "the number of normals differs from the number of positions"
+        );
+        assert!(
+            uvs.as_ref().is_none_or(|uvs| uvs.len() == n_vertices),
+            "the number of uvs differs from the number of positions"
+        );
+        assert!(
+            colors
+                .as_ref()
+                .is_none_or(|colors| colors.len() == n_vertices),
+            "the number of colors differs from the number of positions"
+        );
+        assert!(
+            indices.iter().flatten().all(|&k| (k as usize) < n_vertices),
+            "vertex index out of range"
+        );
+
+        let build_primitives = indices
+            .into_iter()
+            .map(|triangle| {
+                let [a, b, c] = triangle.map(|k| &positions[k as usize]);
+                BuildPrimitive::new(triangle, triangle::triangle_bounding_box(a, b, c))
+            })
+            .collect();
+        let (nodes, indices) = bvh_linear::build_nodes(build_primitives, &BvhOptions::new());
+
+        Self {
+            positions,
+            normals,
+            uvs,
+            colors,
+            indices,
+            mat,
+            nodes,
+        }
+    }
+
+    pub fn n_triangles(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn n_vertices(&self) -> usize {
+        self.positions.len()
+    }
+
+    fn vertices<T: Clone>(&self, attribute: &[T], triangle: usize) -> [T; 3] {
+        self.indices[triangle].map(|k| attribute[k as usize].clone())
+    }
+}
+
+impl HittableAaBb for TriangleMesh {
+    fn hit_aabb(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        let mut closest = None;
+        bvh_linear::hit_nodes(&self.nodes, ray, ray_t, |k, ray_t| {
+            let [a, b, c] = self.indices[k].map(|i| &self.positions[i as usize]);
+            let (t, barycentric) = triangle::intersect_triangle(ray, &ray_t, a, b, c)?;
+            closest = Some((k, t, barycentric));
+            Some(t)
+        });
+
+        // attributes only for the closest triangle
+        let Some((k, t, barycentric)) = closest else {
+            return false;
+        };
+        let normals = self
+            .normals
+            .as_ref()
+            .map(|normals| self.vertices(normals, k));
+        let uvs = self.uvs.as_ref().map(|uvs| self.vertices(uvs, k));
+        triangle::set_hit_record(
+            ray,
+            t,
+            barycentric,
+            &self.vertices(&self.positions, k),
+            normals.as_ref(),
+            uvs.as_ref(),
+            rec,
+        );
+        if let Some(colors) = self.colors.as_ref() {
+            let [b0, b1, b2] = barycentric;
+            let [c0, c1, c2] = self.vertices(colors, k);
+            rec.color = Some(b0 * c0 + b1 * c1 + b2 * c2);
+        }
+        rec.mat = self.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> AaBb {
+        bvh_linear::nodes_bounding_box(&self.nodes)
+    }
+}
+
+impl HittableMat for TriangleMesh {
+    fn hit_mat(&self, ray: &Ray, ray_t: Interval, rec: &mut HitRecordMat) -> bool {
+        self.hit_aabb(ray, ray_t, rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable_list_aabb::HittableListAaBb;
+    use crate::triangle::Triangle;
+    use crate::utl;
+
+    #[test]
+    fn test_mesh_hit_same_as_triangles() {
+        // height field over a grid with shared vertices
+        let n = 20;
+        let mut positions = vec![];
+        let mut normals = vec![];
+        for j in 0..=n {
+            for i in 0..=n {
+                let (x, z) = (i as f64 / n as f64 - 0.5, j as f64 / n as f64 - 0.5);
+                positions.push(Point3::from_slice([x, 0.1 * (7.0 * x).sin() * z, z]));
+                normals.push(Vec3::from_slice([0.0, 1.0, 0.0]));
+            }
+        }
+        let mut indices = vec![];
+        for j in 0..n {
+            for i in 0..n {
+                let k = (j * (n + 1) + i) as u32;
+                let row = (n + 1) as u32;
+                indices.push([k, k + row, k + 1]);
+                indices.push([k + 1, k + row, k + row + 1]);
+            }
+        }
+
+        let mut list: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+        for [a, b, c] in indices.iter() {
+            let [a, b, c] = [a, b, c].map(|&k| &positions[k as usize]);
+            list.add(Arc::new(Triangle::new(a, b, c, None)));
+        }
+        let mesh =
+            TriangleMesh::new_with_attributes(positions, Some(normals), None, None, indices, None);
+        assert_eq!(mesh.n_triangles(), 2 * n * n);
+        assert_eq!(mesh.n_vertices(), (n + 1) * (n + 1));
+        let bbox = mesh.bounding_box();
+        assert_eq!((bbox.x.min, bbox.x.max), (-0.5, 0.5));
+
+        let mut random = utl::Random::new_by_seed(2);
+        let origin = Point3::from_slice([0.3, 2.0, 1.0]);
+        for _ in 0..1000 {
+            let target = Point3::from_slice([
+                random.random_f64_range(-0.6, 0.6),
+                0.0,
+                random.random_f64_range(-0.6, 0.6),
+            ]);
+            let ray = Ray::from_origin_dir(&origin, &(target - origin.clone()));
+
+            let mut rec_list = HitRecordMat::new();
+            let mut rec_mesh = HitRecordMat::new();
+            let ray_t = Interval::new_by_value(0.001, utl::constans::INFINITY);
+            let hit_list = list.hit_aabb(&ray, ray_t.clone(), &mut rec_list);
+            let hit_mesh = mesh.hit_aabb(&ray, ray_t, &mut rec_mesh);
+
+            assert_eq!(hit_list, hit_mesh);
+            if hit_list {
+                assert!((rec_list.t - rec_mesh.t).abs() < 1e-12);
+                assert_eq!(rec_mesh.normal.e, [0.0, 1.0, 0.0]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sphere_in_front_of_colored_mesh() {
+        use crate::bvh_linear::LinearBvh;
+        use crate::interval::consts;
+        use crate::sphere_aabb::SphereAaBb;
+
+        // the mesh comes first, so its color is in the record when the sphere is hit
+        let scene = || {
+            let mesh = TriangleMesh::new_with_attributes(
+                vec![
+                    Point3::from_slice([-2.0, -2.0, -2.0]),
+                    Point3::from_slice([2.0, -2.0, -2.0]),
+                    Point3::from_slice([0.0, 2.0, -2.0]),
+                ],
+                None,
+                None,
+                Some(vec![Color::from_slice([1.0, 0.0, 0.0]); 3]),
+                vec![[0, 1, 2]],
+                None,
+            );
+            let mut list: HittableListAaBb<dyn HittableAaBb> = HittableListAaBb::new();
+            list.add(Arc::new(mesh));
+            list.add(Arc::new(SphereAaBb::new_stationary(
+                &Point3::from_slice([0.0, 0.0, -1.0]),
+                0.5,
+                None,
+            )));
+            list
+        };
+
+        let ray = Ray::from_origin_dir(&Point3::new(), &Vec3::from_slice([0.0, 0.0, -1.0]));
+        let bvh = LinearBvh::new(scene());
+        for scene in [&scene() as &dyn HittableAaBb, &bvh] {
+            let mut rec = HitRecordMat::new();
+            assert!(scene.hit_aabb(&ray, consts::UNIVERSE, &mut rec));
+            assert!((rec.t - 0.5).abs() < 1e-12);
+            assert!(rec.color.is_none());
+            assert_eq!(rec.barycentric, [0.0; 3]);
+            // the sphere's own u, v: the hit point faces +z
+            assert!((rec.u - 0.25).abs() < 1e-12 && (rec.v - 0.5).abs() < 1e-12);
+        }
+    }
+}
@@ -3,40 +3,101 @@ use rand::Rng;
 
 // constans
 pub mod constans {
-    pub const INFINITY: f64 = std::f64::MAX;
+    pub const INFINITY: f64 = f64::MAX;
     pub const PI: f64 = std::f64::consts::PI;
 }
 
-pub fn random_f64() -> f64 {
-    let mut rng = rand::thread_rng();
-    rng.gen_range(0.0..1.0)
-}
-
-pub fn random_f64_range(min: f64, max: f64) -> f64 {
-    let mut rng = rand::thread_rng();
-    rng.gen_range(min..max)
-}
-
-pub fn random_i32(min: f64, max: f64) -> i32 {
-    (min + (max - min) as f64 * random_f64()) as i32
+/// seedable random number generator (PCG32, XSH RR)
+///
+/// the same seed always gives the same sequence,
+/// so renders are reproducible regardless of the number of threads.
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+    inc: u64,
 }
 
-pub struct Random {
-    rng: rand::rngs::ThreadRng,
+impl Default for Random {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Random {
+    /// initialize with a seed from the OS entropy
     pub fn new() -> Self {
-        Self {
-            rng: rand::thread_rng(),
-        }
+        Self::new_by_seed(rand::thread_rng().gen())
+    }
+
+    /// initialize with a fixed seed
+    pub fn new_by_seed(seed: u64) -> Self {
+        Self::new_by_seed_stream(seed, 0)
+    }
+
+    /// initialize for sample `sample` of pixel (i, j) of a render with `seed`
+    pub fn new_for_sample(seed: u64, i: i32, j: i32, sample: i32) -> Self {
+        let pixel = ((i as u32 as u64) << 32) | j as u32 as u64;
+        Self::new_by_seed_stream(splitmix64(seed ^ splitmix64(pixel)), sample as u32 as u64)
+    }
+
+    fn new_by_seed_stream(seed: u64, stream: u64) -> Self {
+        let mut random = Self {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        random.next_u32();
+        random.state = random.state.wrapping_add(seed);
+        random.next_u32();
+
+        random
     }
 
+    pub fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(self.inc);
+
+        let xor_shifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rot = (old_state >> 59) as u32;
+        xor_shifted.rotate_right(rot)
+    }
+
+    /// random value in [0, 1)
     pub fn random_f64(&mut self) -> f64 {
-        self.rng.gen_range(0.0..1.0)
+        let hi = (self.next_u32() >> 5) as u64;
+        let lo = (self.next_u32() >> 6) as u64;
+        ((hi << 26) | lo) as f64 / (1u64 << 53) as f64
     }
 
+    /// random value in [min, max)
     pub fn random_f64_range(&mut self, min: f64, max: f64) -> f64 {
-        self.rng.gen_range(min..max)
+        min + (max - min) * self.random_f64()
+    }
+}
+
+/// SplitMix64 finalizer: used to decorrelate seeds
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_by_seed() {
+        let mut random_1 = Random::new_by_seed(42);
+        let mut random_2 = Random::new_by_seed(42);
+        for _ in 0..100 {
+            assert_eq!(random_1.random_f64(), random_2.random_f64());
+        }
+
+        let mut random_3 = Random::new_for_sample(42, 3, 4, 0);
+        let mut random_4 = Random::new_for_sample(42, 3, 4, 1);
+        assert_ne!(random_3.random_f64(), random_4.random_f64());
     }
 }
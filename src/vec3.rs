@@ -9,6 +9,12 @@ pub struct Vec3 {
 }
 pub type Point3 = Vec3;
 
+impl Default for Vec3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Vec3 {
     pub fn new() -> Self {
         Self { e: [0.0, 0.0, 0.0] }
@@ -26,8 +32,7 @@ impl Vec3 {
     }
 
     /// initialize with random values between 0.0 and 1.0
-    pub fn random() -> Self {
-        let mut random = utl::Random::new();
+    pub fn random(random: &mut utl::Random) -> Self {
         Self {
             e: [
                 random.random_f64(),
@@ -38,8 +43,7 @@ impl Vec3 {
     }
 
     /// initialize with random values between min and max
-    pub fn random_by_range(min: f64, max: f64) -> Self {
-        let mut random = utl::Random::new();
+    pub fn random_by_range(min: f64, max: f64, random: &mut utl::Random) -> Self {
         Self {
             e: [
                 random.random_f64_range(min, max),
@@ -49,35 +53,34 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_unit_sphere() -> Self {
+    pub fn random_in_unit_sphere(random: &mut utl::Random) -> Self {
         loop {
-            let p = Self::random_by_range(-1.0, 1.0);
+            let p = Self::random_by_range(-1.0, 1.0, random);
             if p.norm() < 1.0 {
                 return p;
             }
         }
     }
 
-    pub fn random_on_hemisphere(normal: &Vec3) -> Self {
-        let on_unit_sphere = Self::random_unit_vector();
+    pub fn random_on_hemisphere(normal: &Vec3, random: &mut utl::Random) -> Self {
+        let on_unit_sphere = Self::random_unit_vector(random);
 
-        if on_unit_sphere.dot(&normal) > 0.0 {
+        if on_unit_sphere.dot(normal) > 0.0 {
             on_unit_sphere
         } else {
             -on_unit_sphere
         }
     }
 
-    pub fn random_unit_vector() -> Self {
-        Self::new_unit_vec(Self::random_in_unit_sphere())
+    pub fn random_unit_vector(random: &mut utl::Random) -> Self {
+        Self::new_unit_vec(Self::random_in_unit_sphere(random))
     }
 
     pub fn reflect(v: &Self, n: &Self) -> Self {
-        v.clone() - 2.0 * v.dot(&n) * n.clone()
+        v.clone() - 2.0 * v.dot(n) * n.clone()
     }
 
-    pub fn random_in_unit_disk() -> Vec3 {
-        let mut random = utl::Random::new();
+    pub fn random_in_unit_disk(random: &mut utl::Random) -> Vec3 {
         loop {
             let p = Vec3::from_slice([
                 random.random_f64_range(-1.0, 1.0),
@@ -91,7 +94,7 @@ impl Vec3 {
     }
 
     pub fn refract(uv: &Self, n: &Self, etai_over_etat: f64) -> Self {
-        let cos_theta = (-uv.dot(&n)).min(1.0);
+        let cos_theta = (-uv.dot(n)).min(1.0);
         let r_out_perp = etai_over_etat * (uv.clone() + cos_theta * n.clone());
         let r_out_parallel = -((1.0 - r_out_perp.norm_squared()).abs().sqrt()) * n.clone();
 
@@ -292,7 +295,7 @@ mod tests {
         let vec_neg = -vec;
         let vec_ans = Vec3::from_slice([-1.0, -2.0, -3.0]);
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec_neg.x() - vec_ans.x()).abs() < epsilon
                 && (vec_ans.y() - vec_neg.y()).abs() < epsilon
@@ -305,7 +308,7 @@ mod tests {
         let vec = Vec3::from_slice([1.0, 2.0, 3.0]);
         let ans = [1.0, 2.0, 3.0];
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec[0] - ans[0]).abs() < epsilon
                 && (vec[1] - ans[1]).abs() < epsilon
@@ -322,7 +325,7 @@ mod tests {
         vec[1] = 22.0;
         vec[2] = 33.0;
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec[0] - ans[0]).abs() < epsilon
                 && (vec[1] - ans[1]).abs() < epsilon
@@ -337,7 +340,7 @@ mod tests {
 
         let vec_3 = vec_1 + vec_2;
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec_3[0] - ans[0]).abs() < epsilon
                 && (vec_3[1] - ans[1]).abs() < epsilon
@@ -353,7 +356,7 @@ mod tests {
 
         let vec_3 = vec_1 * vec_2;
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec_3[0] - ans[0]).abs() < epsilon
                 && (vec_3[1] - ans[1]).abs() < epsilon
@@ -369,7 +372,7 @@ mod tests {
         let vec_3 = vec_1.clone() * 2.0;
         let vec_4 = 2.0 * vec_1;
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec_3[0] - ans[0]).abs() < epsilon
                 && (vec_3[1] - ans[1]).abs() < epsilon
@@ -389,7 +392,7 @@ mod tests {
 
         let vec_2 = vec_1 / 2.0;
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec_2[0] - ans[0]).abs() < epsilon
                 && (vec_2[1] - ans[1]).abs() < epsilon
@@ -405,7 +408,7 @@ mod tests {
 
         let vec_3 = vec_1 - vec_2;
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec_3[0] - ans[0]).abs() < epsilon
                 && (vec_3[1] - ans[1]).abs() < epsilon
@@ -422,7 +425,7 @@ mod tests {
         println!("vec_1: {:?}", vec_1);
         vec_1 += vec_2;
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec_1[0] - ans[0]).abs() < epsilon
                 && (vec_1[1] - ans[1]).abs() < epsilon
@@ -436,7 +439,7 @@ mod tests {
         let ans = Vec3::from_slice([2.0, 4.0, 6.0]);
         vec_1 *= 2.0;
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec_1[0] - ans[0]).abs() < epsilon
                 && (vec_1[1] - ans[1]).abs() < epsilon
@@ -450,7 +453,7 @@ mod tests {
         let ans = Vec3::from_slice([1.0, 2.0, 3.0]);
         vec_1 /= 2.0;
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!(
             (vec_1[0] - ans[0]).abs() < epsilon
                 && (vec_1[1] - ans[1]).abs() < epsilon
@@ -467,31 +470,33 @@ mod tests {
         let dot_product_1 = vec_1.dot(&vec_2);
         let dot_product_2 = dot(&vec_1, &vec_2);
 
-        let epsilon = std::f64::EPSILON;
+        let epsilon = f64::EPSILON;
         assert!((ans - dot_product_1).abs() < epsilon);
         assert!((ans - dot_product_2).abs() < epsilon);
     }
 
     #[test]
     fn test_random() {
-        let vec_random_01 = Vec3::random();
+        let mut random = utl::Random::new_by_seed(1);
+        let vec_random_01 = Vec3::random(&mut random);
         for v in vec_random_01.e {
-            assert!(v >= 0.0 && v < 1.0);
+            assert!((0.0..1.0).contains(&v));
         }
 
         let (min, max) = (1.5, 5.6);
-        let vec_random_range = Vec3::random_by_range(min, max);
+        let vec_random_range = Vec3::random_by_range(min, max, &mut random);
         for v in vec_random_range.e {
-            assert!(v >= min && v < max);
+            assert!((min..max).contains(&v));
         }
     }
 
     #[test]
     fn test_random_in_unit_sphere() {
-        let vec_in_unit_sphere = Vec3::random_in_unit_sphere();
+        let mut random = utl::Random::new_by_seed(1);
+        let vec_in_unit_sphere = Vec3::random_in_unit_sphere(&mut random);
         assert!(vec_in_unit_sphere.norm_squared() < 1.0);
 
-        let vec_to_unit_sphere = Vec3::random_unit_vector();
-        assert!((vec_to_unit_sphere.norm_squared() - 1.0).abs() < std::f64::EPSILON);
+        let vec_to_unit_sphere = Vec3::random_unit_vector(&mut random);
+        assert!((vec_to_unit_sphere.norm_squared() - 1.0).abs() < f64::EPSILON);
     }
 }
@@ -0,0 +1,288 @@
+//! zlib (RFC 1950) stream compressed with deflate (RFC 1951)
+//!
+//! deflate uses LZ77 with hash chains and the fixed Huffman codes,
+//! which is enough for rendered images and keeps the encoder small.
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// compress `data` into a zlib stream
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    // CMF: deflate with 32K window, FLG: no dictionary, default level, (CMF * 256 + FLG) % 31 == 0
+    let mut out = vec![0x78, 0x9c];
+    out.extend_from_slice(&deflate(data));
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+
+    out
+}
+
+/// Adler-32 checksum of `data`
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest n such that b does not overflow before the modulo
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+
+    (b << 16) | a
+}
+
+/// raw deflate stream of `data` as one block with the fixed Huffman codes
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitWriter::new();
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman)
+    bits.write_bits(1, 1);
+    bits.write_bits(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+    let hash = |pos: usize| {
+        let v = (data[pos] as u32) << 16 | (data[pos + 1] as u32) << 8 | data[pos + 2] as u32;
+        (v.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+    };
+    let insert = |pos: usize, head: &mut [usize], prev: &mut [usize]| {
+        if pos + MIN_MATCH <= data.len() {
+            let h = hash(pos);
+            prev[pos % WINDOW_SIZE] = head[h];
+            head[h] = pos;
+        }
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let (length, distance) = longest_match(data, pos, &head, &prev, hash);
+        if length >= MIN_MATCH {
+            write_length(&mut bits, length);
+            write_distance(&mut bits, distance);
+            for p in pos..pos + length {
+                insert(p, &mut head, &mut prev);
+            }
+            pos += length;
+        } else {
+            write_literal(&mut bits, data[pos] as u16);
+            insert(pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+    write_literal(&mut bits, 256);
+
+    bits.finish()
+}
+
+/// longest earlier occurrence of the bytes at `pos` within the window: (length, distance)
+fn longest_match(
+    data: &[u8],
+    pos: usize,
+    head: &[usize],
+    prev: &[usize],
+    hash: impl Fn(usize) -> usize,
+) -> (usize, usize) {
+    if pos + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+    let max_length = MAX_MATCH.min(data.len() - pos);
+    let (mut best_length, mut best_distance) = (0, 0);
+
+    let mut candidate = head[hash(pos)];
+    let mut chain = 0;
+    while candidate != usize::MAX && pos - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+        let length = data[candidate..]
+            .iter()
+            .zip(&data[pos..pos + max_length])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if length > best_length {
+            (best_length, best_distance) = (length, pos - candidate);
+            if length == max_length {
+                break;
+            }
+        }
+
+        let next = prev[candidate % WINDOW_SIZE];
+        // entries older than the window have been overwritten
+        if next == usize::MAX || next >= candidate {
+            break;
+        }
+        candidate = next;
+        chain += 1;
+    }
+
+    (best_length, best_distance)
+}
+
+/// fixed Huffman code of literal/length symbol 0..=287
+fn write_literal(bits: &mut BitWriter, symbol: u16) {
+    let (code, n_bits) = match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    };
+    bits.write_huffman(code as u32, n_bits);
+}
+
+fn write_length(bits: &mut BitWriter, length: usize) {
+    let index = LENGTH_BASE
+        .iter()
+        .rposition(|&base| base as usize <= length)
+        .unwrap();
+    write_literal(bits, 257 + index as u16);
+    bits.write_bits(
+        (length - LENGTH_BASE[index] as usize) as u32,
+        LENGTH_EXTRA[index] as u32,
+    );
+}
+
+fn write_distance(bits: &mut BitWriter, distance: usize) {
+    let index = DIST_BASE
+        .iter()
+        .rposition(|&base| base as usize <= distance)
+        .unwrap();
+    bits.write_huffman(index as u32, 5);
+    bits.write_bits(
+        (distance - DIST_BASE[index] as usize) as u32,
+        DIST_EXTRA[index] as u32,
+    );
+}
+
+/// packs bits starting from the least significant bit of each byte
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u64,
+    n_bits: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            out: vec![],
+            buffer: 0,
+            n_bits: 0,
+        }
+    }
+
+    /// `n_bits` low bits of `value`, least significant bit first
+    fn write_bits(&mut self, value: u32, n_bits: u32) {
+        self.buffer |= (value as u64) << self.n_bits;
+        self.n_bits += n_bits;
+        while self.n_bits >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.n_bits -= 8;
+        }
+    }
+
+    /// Huffman codes are packed most significant bit first
+    fn write_huffman(&mut self, code: u32, n_bits: u32) {
+        self.write_bits(code.reverse_bits() >> (32 - n_bits), n_bits);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.n_bits > 0 {
+            self.out.push(self.buffer as u8);
+        }
+
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// minimal inflate of the blocks written by `deflate`
+    fn inflate_fixed(data: &[u8]) -> Vec<u8> {
+        let mut pos = 0;
+        let mut read_bits = |n: u32| {
+            let mut value = 0;
+            for k in 0..n {
+                value |= ((data[pos / 8] >> (pos % 8)) as u32 & 1) << k;
+                pos += 1;
+            }
+            value
+        };
+        assert_eq!(read_bits(1), 1);
+        assert_eq!(read_bits(2), 1);
+
+        let mut out: Vec<u8> = vec![];
+        loop {
+            let mut code = 0;
+            let mut n_bits = 0;
+            let symbol = loop {
+                code = (code << 1) | read_bits(1);
+                n_bits += 1;
+                match (n_bits, code) {
+                    (7, 0..=0x17) => break code + 256,
+                    (8, 0x30..=0xbf) => break code - 0x30,
+                    (8, 0xc0..=0xc7) => break code - 0xc0 + 280,
+                    (9, 0x190..=0x1ff) => break code - 0x190 + 144,
+                    _ => {}
+                }
+            };
+            match symbol {
+                0..=255 => out.push(symbol as u8),
+                256 => break,
+                _ => {
+                    let index = (symbol - 257) as usize;
+                    let length = LENGTH_BASE[index] as usize
+                        + read_bits(LENGTH_EXTRA[index] as u32) as usize;
+                    let dist_index = read_bits(5).reverse_bits() >> 27;
+                    let distance = DIST_BASE[dist_index as usize] as usize
+                        + read_bits(DIST_EXTRA[dist_index as usize] as u32) as usize;
+                    for _ in 0..length {
+                        out.push(out[out.len() - distance]);
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn test_deflate_round_trip() {
+        let mut data = b"abcabcabcabcabcabc hello hello hello".to_vec();
+        data.extend((0..100000u64).map(|k| ((k * k) % 251 % 7) as u8));
+        data.extend((0..100000u32).map(|k| (k / 300 + k % 3) as u8));
+        data.extend(vec![42; 1000]);
+
+        let compressed = deflate(&data);
+        assert!(compressed.len() < data.len() / 2);
+        assert_eq!(inflate_fixed(&compressed), data);
+        assert_eq!(inflate_fixed(&deflate(b"")), b"");
+
+        let stream = compress(&data);
+        assert_eq!(((stream[0] as u32) << 8 | stream[1] as u32) % 31, 0);
+        assert_eq!(stream[stream.len() - 4..], adler32(&data).to_be_bytes());
+    }
+}